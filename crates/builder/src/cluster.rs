@@ -0,0 +1,114 @@
+//! Cellular-automaton smoothing that grows scattered terrain into clusters.
+
+use world_core::TileType;
+
+use crate::WorldBuilder;
+
+/// Settings for `WorldBuilder::cluster_terrain`.
+///
+/// On each pass a `background` tile with at least `birth` surrounding tiles of
+/// one of `types` becomes that type, and a tile of one of `types` with fewer
+/// than `survival` matching neighbours reverts to `background`.
+#[derive(Debug)]
+pub struct ClusterConfig {
+    pub passes: u32,
+    pub birth: usize,
+    pub survival: usize,
+    pub types: Vec<TileType>,
+    pub background: TileType,
+}
+
+impl Default for ClusterConfig {
+    fn default() -> ClusterConfig {
+        ClusterConfig {
+            passes: 2,
+            birth: 3,
+            survival: 1,
+            types: vec![TileType::Forest, TileType::Mountain],
+            background: TileType::Plains,
+        }
+    }
+}
+
+/// Runs a single smoothing pass over the builder's world.
+pub fn smooth(builder: &mut WorldBuilder, config: &ClusterConfig) {
+    let world = &builder.world;
+    let mut next = world.tiles.clone();
+
+    for i in 0..world.tiles.len() {
+        let rcp_i = builder.get_reciprocal_by_index(i);
+        if rcp_i < i
+            || !is_clusterable(builder, i, config)
+            || !is_clusterable(builder, rcp_i, config)
+        {
+            continue;
+        }
+
+        let surrounding = world.get_surrounding(&world.coords_from_index(i));
+        let count = |tile_type: &TileType| {
+            surrounding
+                .iter()
+                .filter(|coords| world.tile_at_coords(coords) == tile_type)
+                .count()
+        };
+
+        let current = world.tile_at(i);
+        let tile_type = if config.types.contains(current) {
+            if count(current) < config.survival {
+                config.background
+            } else {
+                *current
+            }
+        } else {
+            config
+                .types
+                .iter()
+                .map(|tile_type| (tile_type, count(tile_type)))
+                .filter(|(_, count)| *count >= config.birth)
+                .max_by_key(|(_, count)| *count)
+                .map_or(*current, |(tile_type, _)| *tile_type)
+        };
+
+        next[i] = tile_type;
+        next[rcp_i] = tile_type;
+    }
+
+    builder.world.tiles = next;
+}
+
+fn is_clusterable(builder: &WorldBuilder, i: usize, config: &ClusterConfig) -> bool {
+    let tile_type = builder.world.tile_at(i);
+    !builder.is_locked(i) && (config.types.contains(tile_type) || *tile_type == config.background)
+}
+
+#[test]
+fn test_isolated_tiles_revert_to_background() {
+    use crate::Config;
+
+    let mut builder = WorldBuilder::new(Config {
+        height: 5,
+        width: 5,
+    });
+    builder.world.tiles = vec![TileType::Plains; 25];
+    builder.world.tiles[6] = TileType::Forest;
+    builder.world.tiles[18] = TileType::Forest;
+
+    smooth(&mut builder, &ClusterConfig::default());
+    assert!(builder.world.tiles.iter().all(|t| *t == TileType::Plains));
+}
+
+#[test]
+fn test_locked_tiles_are_unchanged() {
+    use crate::Config;
+
+    let mut builder = WorldBuilder::new(Config {
+        height: 5,
+        width: 5,
+    });
+    builder.world.tiles = vec![TileType::Forest; 25];
+    builder.world.tiles[12] = TileType::Plains;
+    builder.lock(12);
+
+    smooth(&mut builder, &ClusterConfig::default());
+    assert_eq!(builder.world.tiles[12], TileType::Plains);
+}
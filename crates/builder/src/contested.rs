@@ -0,0 +1,103 @@
+//! Neutral structures, such as com towers and labs, placed where both
+//! players have an equal claim to them.
+
+use world_core::{MovementType, TileType};
+
+use crate::fields::contestedness;
+use crate::{GenerationError, WorldBuilder};
+
+/// Places `count` neutral `tile_type` tiles, and their reciprocals, on the
+/// most contested free tiles. Structures are kept from touching each other
+/// so they don't all end up in one cluster on the front line.
+pub fn place_contested(
+    builder: &mut WorldBuilder,
+    tile_type: TileType,
+    count: u32,
+    movement: MovementType,
+) -> Result<(), GenerationError> {
+    if builder.world.find_headquarters().len() < 2 {
+        return Err(GenerationError::MissingHeadquarters);
+    }
+    let scores = contestedness(&builder.world, movement);
+    let mut candidates: Vec<usize> = (0..builder.world.tiles.len())
+        .filter(|&i| {
+            scores[i].is_finite()
+                && is_free(builder, i)
+                && is_free(builder, builder.get_reciprocal_by_index(i))
+        })
+        .collect();
+    candidates.sort_by(|&a, &b| scores[b].total_cmp(&scores[a]));
+
+    let mut placed = 0;
+    for i in candidates {
+        if placed == count {
+            break;
+        }
+        let rcp_i = builder.get_reciprocal_by_index(i);
+        let world = &builder.world;
+        let crowded = [i, rcp_i].iter().any(|&i| {
+            world
+                .get_neighbours(&world.coords_from_index(i))
+                .iter()
+                .flatten()
+                .any(|n| *world.tile_at_coords(n) == tile_type)
+        });
+        if crowded || !is_free(builder, i) || !is_free(builder, rcp_i) {
+            continue;
+        }
+        for i in [i, rcp_i] {
+            builder.world.update_tile_by_index(i, tile_type);
+            builder.lock(i);
+        }
+        placed += 1;
+    }
+    if placed < count {
+        return Err(GenerationError::NotEnoughSpace {
+            tile_type,
+            placed,
+            requested: count,
+        });
+    }
+    Ok(())
+}
+
+pub(crate) fn is_free(builder: &WorldBuilder, i: usize) -> bool {
+    !builder.is_locked(i)
+        && matches!(
+            builder.world.tile_at(i),
+            TileType::Empty | TileType::Plains | TileType::Forest
+        )
+}
+
+#[test]
+fn test_com_towers_are_contested() {
+    use crate::Config;
+    use world_core::{Coordinates, Player};
+
+    let mut builder = WorldBuilder::new(Config {
+        height: 1,
+        width: 9,
+    });
+    builder
+        .world
+        .update_tile_by_index(0, TileType::Hq(Player::One));
+    builder
+        .world
+        .update_tile_by_index(8, TileType::Hq(Player::Two));
+    builder.lock(0);
+    builder.lock(8);
+
+    let builder = builder.try_add_com_towers(1).unwrap();
+    let tower = builder.world.index_from_coords(&Coordinates::new(4, 0));
+    assert_eq!(*builder.world.tile_at(tower), TileType::ComTower(None));
+    assert!(builder.is_locked(tower));
+
+    assert!(matches!(
+        builder.try_add_labs(5),
+        Err(GenerationError::NotEnoughSpace {
+            tile_type: TileType::Lab(None),
+            requested: 5,
+            ..
+        })
+    ));
+}
@@ -0,0 +1,91 @@
+use world_core::{Players, Symmetry, TileType, Tileset, UnitType, WorldError};
+
+use crate::fairness::FairnessReport;
+use crate::teams::Teams;
+use crate::terrain::TerrainBudget;
+use crate::validation::ValidationReport;
+
+#[derive(Debug, thiserror::Error)]
+pub enum GenerationError {
+    #[error(transparent)]
+    World(#[from] WorldError),
+    #[error("density must be between 0.0 and 1.0, got {0}")]
+    InvalidDensity(f32),
+    #[error(
+        "terrain fractions must each be between 0.0 and 1.0 and add up to at most 1.0, got {0:?}"
+    )]
+    InvalidTerrainBudget(TerrainBudget),
+    #[error("template tile ({x}, {y}) doesn't match its reciprocal")]
+    AsymmetricTemplate { x: u32, y: u32 },
+    #[error("{symmetry:?} symmetry needs a square map, not {width}x{height}")]
+    UnsupportedSymmetry {
+        symmetry: Symmetry,
+        width: u32,
+        height: u32,
+    },
+    #[error("teams must be two equal sides of different players, got {0:?}")]
+    InvalidTeams(Teams),
+    #[error("teams mirror each other, so the map can't be generated without symmetry")]
+    TeamsNeedSymmetry,
+    #[error("headquarters are placed in opposing pairs, so {0:?} players isn't supported")]
+    UnpairedPlayers(Players),
+    #[error("{tile_type:?} isn't part of the {tileset:?} tileset")]
+    UnsupportedTile {
+        tile_type: TileType,
+        tileset: Tileset,
+    },
+    #[error("couldn't place headquarters within the constraints after {0} attempts")]
+    HeadquartersPlacement(u32),
+    #[error("only room for {placed} of {requested} {tile_type:?} tiles")]
+    NotEnoughSpace {
+        tile_type: TileType,
+        placed: u32,
+        requested: u32,
+    },
+    #[error("no room to deploy {0:?} near headquarters")]
+    UnitPlacement(UnitType),
+    #[error(
+        "map is too small for the property budget: needs {needed} free tiles, has {available}"
+    )]
+    MapTooSmall { needed: u32, available: u32 },
+    #[error(
+        "property budget has {count} {tile_type:?} tiles, but no free tile is its own reciprocal to take the odd one"
+    )]
+    UnmirrorableBudget { tile_type: TileType, count: u32 },
+    #[error("couldn't find headquarters")]
+    MissingHeadquarters,
+    #[error("path between headquarters is incomplete")]
+    IncompletePath,
+    #[error("no route between headquarters")]
+    NoRoute,
+    #[error("WFC contradicted itself on every attempt, so some adjacency rules were relaxed")]
+    WfcContradiction,
+    #[error("items and weights have different lengths ({items} and {weights})")]
+    WeightMismatch { items: usize, weights: usize },
+    #[error("generated map is invalid: {0:?}")]
+    InvalidMap(ValidationReport),
+    #[error("couldn't balance the map for every player: {:?}", .0.players)]
+    Unbalanced(FairnessReport),
+}
+
+impl GenerationError {
+    /// Recoverable errors skip or degrade a single step and are collected as
+    /// warnings. Anything else makes `build` fail.
+    pub fn is_recoverable(&self) -> bool {
+        matches!(
+            self,
+            GenerationError::InvalidDensity(_)
+                | GenerationError::InvalidTerrainBudget(_)
+                | GenerationError::UnsupportedTile { .. }
+                | GenerationError::NotEnoughSpace { .. }
+                | GenerationError::MapTooSmall { .. }
+                | GenerationError::UnmirrorableBudget { .. }
+                | GenerationError::UnitPlacement(_)
+                | GenerationError::MissingHeadquarters
+                | GenerationError::IncompletePath
+                | GenerationError::NoRoute
+                | GenerationError::WfcContradiction
+                | GenerationError::Unbalanced(_)
+        )
+    }
+}
@@ -0,0 +1,266 @@
+//! Per-player balance metrics, and compensation for maps generated without
+//! symmetry.
+
+use rand::seq::SliceRandom;
+use world_core::{MovementType, Player, Symmetry, TileType, World};
+
+use crate::teams::Teams;
+use crate::validation::movement_costs_from;
+use crate::{GenerationError, WorldBuilder};
+
+const MAX_BALANCE_PASSES: u32 = 50;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Metric {
+    Properties,
+    Territory,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PlayerMetrics {
+    pub player: Player,
+    /// Properties, other than headquarters, that are closer to this player's
+    /// headquarters than to any other.
+    pub properties: usize,
+    /// Tiles that are closer to this player's headquarters than to any other.
+    pub territory: usize,
+}
+
+impl PlayerMetrics {
+    fn get(&self, metric: Metric) -> usize {
+        match metric {
+            Metric::Properties => self.properties,
+            Metric::Territory => self.territory,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct FairnessReport {
+    pub players: Vec<PlayerMetrics>,
+    /// The player each tile is closest to, by movement cost. Ties and
+    /// unreachable tiles belong to nobody.
+    pub nearest: Vec<Option<Player>>,
+}
+
+impl FairnessReport {
+    /// Whether, for every metric, the worst-off player is within `tolerance`
+    /// (a fraction of the best-off player's value) of the best-off.
+    pub fn is_balanced(&self, tolerance: f32) -> bool {
+        self.disadvantaged(tolerance).is_none()
+    }
+
+    /// Combines teammates' metrics and territory, so teams are compared
+    /// rather than players. Each team is named after its first player.
+    pub fn by_team(&self, teams: &Teams) -> FairnessReport {
+        if teams.is_empty() {
+            return self.clone();
+        }
+        let leader = |player: Player| {
+            teams
+                .team_of(player)
+                .map_or(player, |team| teams.0[team][0])
+        };
+        let mut players: Vec<PlayerMetrics> = Vec::new();
+        for metrics in &self.players {
+            let player = leader(metrics.player);
+            match players.iter_mut().find(|team| team.player == player) {
+                Some(team) => {
+                    team.properties += metrics.properties;
+                    team.territory += metrics.territory;
+                }
+                None => players.push(PlayerMetrics {
+                    player,
+                    ..metrics.clone()
+                }),
+            }
+        }
+        FairnessReport {
+            players,
+            nearest: self.nearest.iter().map(|owner| owner.map(leader)).collect(),
+        }
+    }
+
+    /// The worst-off player on the first metric that is out of tolerance.
+    pub fn disadvantaged(&self, tolerance: f32) -> Option<(Player, Metric)> {
+        [Metric::Properties, Metric::Territory]
+            .into_iter()
+            .find_map(|metric| Some((self.worst_off(metric, tolerance)?, metric)))
+    }
+
+    /// The worst-off player on `metric`, if they are out of tolerance.
+    pub fn worst_off(&self, metric: Metric, tolerance: f32) -> Option<Player> {
+        let worst = self.players.iter().min_by_key(|m| m.get(metric))?;
+        let best = self.players.iter().map(|m| m.get(metric)).max()?;
+        let gap = (best - worst.get(metric)) as f32;
+        (gap > tolerance * best as f32).then_some(worst.player)
+    }
+}
+
+pub fn analyse(world: &World, movement: MovementType) -> FairnessReport {
+    let headquarters: Vec<(Player, Vec<Option<u32>>)> = world
+        .find_headquarters()
+        .iter()
+        .filter_map(|hq| {
+            let player = world.tile_at_coords(hq).owner()?;
+            Some((player, movement_costs_from(world, hq, movement)))
+        })
+        .collect();
+
+    let nearest: Vec<Option<Player>> = (0..world.tiles.len())
+        .map(|i| {
+            let best = headquarters
+                .iter()
+                .filter_map(|(_, costs)| costs[i])
+                .min()?;
+            let mut closest = headquarters
+                .iter()
+                .filter(|(_, costs)| costs[i] == Some(best));
+            match (closest.next(), closest.next()) {
+                (Some((player, _)), None) => Some(*player),
+                _ => None,
+            }
+        })
+        .collect();
+
+    let players = headquarters
+        .iter()
+        .map(|(player, _)| {
+            let owned = |i: &usize| nearest[*i] == Some(*player);
+            PlayerMetrics {
+                player: *player,
+                properties: (0..world.tiles.len())
+                    .filter(owned)
+                    .filter(|&i| {
+                        let tile = world.tile_at(i);
+                        tile.is_property() && !matches!(tile, TileType::Hq(_))
+                    })
+                    .count(),
+                territory: (0..world.tiles.len()).filter(owned).count(),
+            }
+        })
+        .collect();
+    FairnessReport { players, nearest }
+}
+
+/// Adds cities and clears terrain on the side of whichever player is worst
+/// off until the map is within `tolerance`. Symmetric maps are only checked,
+/// as any change would be mirrored for the other side too.
+pub fn balance(
+    builder: &mut WorldBuilder,
+    tolerance: f32,
+    movement: MovementType,
+) -> Result<(), GenerationError> {
+    for _ in 0..MAX_BALANCE_PASSES {
+        let report = analyse(&builder.world, movement).by_team(&builder.teams);
+        let Some((player, metric)) = report.disadvantaged(tolerance) else {
+            return Ok(());
+        };
+        let compensated = builder.symmetry == Symmetry::None
+            && match metric {
+                Metric::Properties => add_city(builder, &report, player),
+                Metric::Territory => clear_terrain(builder, &report, player, movement),
+            };
+        if !compensated {
+            return Err(GenerationError::Unbalanced(report));
+        }
+        builder.record_retry();
+    }
+    Err(GenerationError::Unbalanced(
+        analyse(&builder.world, movement).by_team(&builder.teams),
+    ))
+}
+
+fn add_city(builder: &mut WorldBuilder, report: &FairnessReport, player: Player) -> bool {
+    let candidates: Vec<usize> = (0..builder.world.tiles.len())
+        .filter(|&i| {
+            report.nearest[i] == Some(player)
+                && !builder.is_locked(i)
+                && matches!(
+                    builder.world.tile_at(i),
+                    TileType::Plains | TileType::Forest | TileType::Empty
+                )
+        })
+        .collect();
+    let Some(&i) = candidates.choose(&mut rand::thread_rng()) else {
+        return false;
+    };
+    builder.world.update_tile_by_index(i, TileType::City(None));
+    builder.lock(i);
+    true
+}
+
+/// Turns the slow or impassable tile closest to `player`'s headquarters, on
+/// the edge of their territory, into plains.
+fn clear_terrain(
+    builder: &mut WorldBuilder,
+    report: &FairnessReport,
+    player: Player,
+    movement: MovementType,
+) -> bool {
+    let world = &builder.world;
+    let Some(hq) = world
+        .find_headquarters()
+        .into_iter()
+        .find(|hq| world.tile_at_coords(hq).owner() == Some(player))
+    else {
+        return false;
+    };
+    let candidate = (0..world.tiles.len())
+        .filter(|&i| {
+            let tile = world.tile_at(i);
+            report.nearest[i] != Some(player)
+                && !builder.is_locked(i)
+                && !tile.is_property()
+                && tile.movement_cost(movement) != Some(1)
+                && world
+                    .get_neighbours(&world.coords_from_index(i))
+                    .iter()
+                    .flatten()
+                    .any(|n| report.nearest[world.index_from_coords(n)] == Some(player))
+        })
+        .min_by(|&a, &b| {
+            let distance = |i| world.coords_from_index(i).distance(&hq);
+            distance(a).total_cmp(&distance(b))
+        });
+    let Some(i) = candidate else {
+        return false;
+    };
+    builder.world.update_tile_by_index(i, TileType::Plains);
+    true
+}
+
+#[test]
+fn test_analyse_and_balance() {
+    use crate::Config;
+
+    let mut builder = WorldBuilder::new(Config {
+        height: 1,
+        width: 7,
+    })
+    .set_symmetry(Symmetry::None);
+    builder.world.tiles = vec![
+        TileType::Hq(Player::One),
+        TileType::City(None),
+        TileType::City(None),
+        TileType::Plains,
+        TileType::Plains,
+        TileType::Plains,
+        TileType::Hq(Player::Two),
+    ];
+
+    let report = analyse(&builder.world, MovementType::Foot);
+    assert_eq!(report.players[0].properties, 2);
+    assert_eq!(report.players[1].properties, 0);
+    assert_eq!(report.players[0].territory, 3);
+    assert_eq!(report.nearest[3], None);
+    assert_eq!(
+        report.disadvantaged(0.1),
+        Some((Player::Two, Metric::Properties))
+    );
+
+    balance(&mut builder, 0.1, MovementType::Foot).unwrap();
+    let report = analyse(&builder.world, MovementType::Foot);
+    assert!(report.is_balanced(0.1));
+    assert_eq!(report.players[1].properties, 2);
+}
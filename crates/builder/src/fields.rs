@@ -0,0 +1,83 @@
+//! Per-tile scalar fields for analysing maps, e.g. to draw as heatmaps.
+//! Tiles without a value, such as unreachable ones, are `NaN`.
+
+use world_core::{MovementType, Player, World};
+
+use crate::validation::movement_costs_from;
+
+/// Cheapest movement cost from `player`'s headquarters to every tile.
+pub fn distance_to_headquarters(world: &World, player: Player, movement: MovementType) -> Vec<f32> {
+    let Some(hq) = world
+        .find_headquarters()
+        .into_iter()
+        .find(|hq| world.tile_at_coords(hq).owner() == Some(player))
+    else {
+        return vec![f32::NAN; world.tiles.len()];
+    };
+    movement_costs_from(world, &hq, movement)
+        .into_iter()
+        .map(|cost| cost.map_or(f32::NAN, |cost| cost as f32))
+        .collect()
+}
+
+/// Movement points needed to enter each tile.
+pub fn movement_cost(world: &World, movement: MovementType) -> Vec<f32> {
+    world
+        .tiles
+        .iter()
+        .map(|tile| {
+            tile.movement_cost(movement)
+                .map_or(f32::NAN, |cost| cost as f32)
+        })
+        .collect()
+}
+
+/// How evenly each tile is contested by the two headquarters closest to it,
+/// from 0.0 (only one can reach it) to 1.0 (both are equally far away).
+pub fn contestedness(world: &World, movement: MovementType) -> Vec<f32> {
+    let distances: Vec<Vec<u32>> = world
+        .find_headquarters()
+        .iter()
+        .map(|hq| {
+            movement_costs_from(world, hq, movement)
+                .into_iter()
+                .map(|cost| cost.unwrap_or(u32::MAX))
+                .collect()
+        })
+        .collect();
+
+    (0..world.tiles.len())
+        .map(|i| {
+            let mut nearest: Vec<u32> = distances.iter().map(|costs| costs[i]).collect();
+            nearest.sort_unstable();
+            match nearest[..] {
+                [u32::MAX, ..] => f32::NAN,
+                [_, u32::MAX, ..] => 0.0,
+                [0, 0, ..] => 1.0,
+                [a, b, ..] => 1.0 - (b - a) as f32 / (a + b) as f32,
+                _ => f32::NAN,
+            }
+        })
+        .collect()
+}
+
+#[test]
+fn test_contestedness() {
+    use world_core::TileType;
+
+    let mut world = World {
+        tiles: vec![TileType::Plains; 5],
+        width: 5,
+        height: 1,
+        units: Vec::new(),
+    };
+    world.tiles[0] = TileType::Hq(Player::One);
+    world.tiles[4] = TileType::Hq(Player::Two);
+
+    let contested = contestedness(&world, MovementType::Foot);
+    assert_eq!(contested[2], 1.0);
+    assert_eq!(contested[0], 0.0);
+    assert_eq!(contested[1], 0.5);
+    let distance = distance_to_headquarters(&world, Player::Two, MovementType::Foot);
+    assert_eq!(distance, vec![4.0, 3.0, 2.0, 1.0, 0.0]);
+}
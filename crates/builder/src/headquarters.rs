@@ -0,0 +1,104 @@
+//! Constraints on where `create_headquarters` may place headquarters.
+
+use world_core::{Coordinates, MovementType, World};
+
+use crate::validation::movement_costs_from;
+
+const DEFAULT_MAX_ATTEMPTS: u32 = 1000;
+const DEFAULT_TERRITORY_TOLERANCE: f32 = 0.1;
+
+/// How the distance between headquarters is measured.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SeparationMetric {
+    /// Straight-line distance in tiles.
+    Tiles,
+    /// Cheapest movement cost over the terrain placed so far.
+    MovementCost(MovementType),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Quadrant {
+    TopLeft,
+    TopRight,
+    BottomLeft,
+    BottomRight,
+}
+
+impl Quadrant {
+    pub fn of(world: &World, coords: &Coordinates) -> Quadrant {
+        let left = coords.x < world.width / 2;
+        let top = coords.y < world.height / 2;
+        match (top, left) {
+            (true, true) => Quadrant::TopLeft,
+            (true, false) => Quadrant::TopRight,
+            (false, true) => Quadrant::BottomLeft,
+            (false, false) => Quadrant::BottomRight,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct HqConstraints {
+    pub min_separation: Option<u32>,
+    pub max_separation: Option<u32>,
+    pub metric: SeparationMetric,
+    /// Tiles to keep clear between a headquarters and the map edge.
+    pub edge_margin: u32,
+    /// Quadrants the first player's headquarters may go in. Empty allows any.
+    pub quadrants: Vec<Quadrant>,
+    /// Without symmetry, how much less territory one player may start with
+    /// than another, as a fraction of the larger. Symmetric placements
+    /// always split the map evenly.
+    pub territory_tolerance: f32,
+    /// Placements to try before giving up with an error.
+    pub max_attempts: u32,
+}
+
+impl Default for HqConstraints {
+    fn default() -> HqConstraints {
+        HqConstraints {
+            min_separation: None,
+            max_separation: None,
+            metric: SeparationMetric::Tiles,
+            edge_margin: 0,
+            quadrants: Vec::new(),
+            territory_tolerance: DEFAULT_TERRITORY_TOLERANCE,
+            max_attempts: DEFAULT_MAX_ATTEMPTS,
+        }
+    }
+}
+
+impl HqConstraints {
+    /// Whether headquarters at `hq` and `other` satisfy every constraint.
+    pub fn allows(&self, world: &World, hq: &Coordinates, other: &Coordinates) -> bool {
+        let within_margin = |coords: &Coordinates| {
+            coords.x >= self.edge_margin
+                && coords.y >= self.edge_margin
+                && coords.x + self.edge_margin < world.width
+                && coords.y + self.edge_margin < world.height
+        };
+        if !within_margin(hq) || !within_margin(other) {
+            return false;
+        }
+        if !self.quadrants.is_empty() && !self.quadrants.contains(&Quadrant::of(world, hq)) {
+            return false;
+        }
+        if self.min_separation.is_none() && self.max_separation.is_none() {
+            return true;
+        }
+
+        let separation = match self.metric {
+            SeparationMetric::Tiles => Some(hq.distance(other)),
+            SeparationMetric::MovementCost(movement) => movement_costs_from(world, hq, movement)
+                [world.index_from_coords(other)]
+            .map(|cost| cost as f32),
+        };
+        // Unreachable headquarters are infinitely far apart.
+        let separation = separation.unwrap_or(f32::INFINITY);
+        self.min_separation
+            .is_none_or(|min| separation >= min as f32)
+            && self
+                .max_separation
+                .is_none_or(|max| separation <= max as f32)
+    }
+}
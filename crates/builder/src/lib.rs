@@ -1,15 +1,74 @@
+use std::cell::{Cell, RefCell};
+use std::time::Instant;
+
 use rand::{self, Rng};
 use visualiser::Visualiser;
-use world_core::{join_tile_collections, Coordinates, Player, Players, Symmetry, TileType, World};
+use world_core::{
+    join_tile_collections, Coordinates, GenerationObserver, MovementType, Player, Players,
+    Symmetry, TileType, Tileset, World,
+};
+pub mod cluster;
+pub mod contested;
+mod error;
+pub mod fairness;
+pub mod fields;
+pub mod headquarters;
+pub mod noise;
+pub mod pipes;
+pub mod presets;
+pub mod properties;
+pub mod ruins;
+pub mod teams;
+pub mod template;
+pub mod terrain;
 mod tile_path;
+pub mod trace;
+pub mod units;
 pub mod utils;
+pub mod validation;
+pub mod wfc;
+use cluster::ClusterConfig;
+pub use error::GenerationError;
+use headquarters::HqConstraints;
+use noise::{NoiseConfig, TerrainFields};
+use presets::Preset;
+use properties::PropertyBudget;
+use ruins::RuinConfig;
+use teams::Teams;
+use terrain::{TerrainBudget, TerrainReport};
+pub use tile_path::PathStrategy;
 use tile_path::*;
+use trace::{GenerationTrace, StepRecord};
+use units::DeployConfig;
+use validation::ValidationMode;
+use wfc::WfcModel;
+
+/// How far apart, on each axis, teammates' headquarters may be.
+const TEAMMATE_SPREAD: u32 = 3;
+/// Without symmetry, how far on each axis an opposite number's headquarters
+/// may stray from the reciprocal spot.
+const COUNTERPART_SPREAD: u32 = 3;
 
 pub struct WorldBuilder {
     pub world: World,
     pub players: Players,
+    pub teams: Teams,
     pub symmetry: Symmetry,
-    pub visualiser: Option<Visualiser>,
+    pub tileset: Tileset,
+    pub terrain_fields: Option<TerrainFields>,
+    /// The last terrain budget applied, reported on at the end of the build.
+    pub terrain_budget: Option<TerrainBudget>,
+    /// Tiles that later steps must not change, indexed like `World::tiles`.
+    pub locked: Vec<bool>,
+    pub validation: ValidationMode,
+    pub path_strategy: PathStrategy,
+    pub hq_constraints: HqConstraints,
+    /// Recoverable problems from earlier steps.
+    pub warnings: Vec<GenerationError>,
+    failure: Option<GenerationError>,
+    pub trace: GenerationTrace,
+    retries: Cell<u32>,
+    observers: RefCell<Vec<Box<dyn GenerationObserver>>>,
 }
 
 pub struct Config {
@@ -20,28 +79,35 @@ pub struct Config {
 #[allow(unused_mut)]
 impl WorldBuilder {
     pub fn new(config: Config) -> WorldBuilder {
-        let tiles: Vec<TileType> = vec![TileType::Empty; (config.height * config.width) as usize];
+        let size = (config.height * config.width) as usize;
+        let tiles: Vec<TileType> = vec![TileType::Empty; size];
         WorldBuilder {
             world: World {
                 height: config.height,
+                units: Vec::new(),
                 width: config.width,
                 tiles,
             },
             players: Players::Two,
+            teams: Teams::default(),
             symmetry: Symmetry::Rotational,
-            visualiser: None,
+            tileset: Tileset::AdvanceWars,
+            terrain_fields: None,
+            terrain_budget: None,
+            locked: vec![false; size],
+            validation: ValidationMode::Off,
+            path_strategy: PathStrategy::RandomWalk,
+            hq_constraints: HqConstraints::default(),
+            warnings: Vec::new(),
+            failure: None,
+            trace: GenerationTrace::default(),
+            retries: Cell::new(0),
+            observers: RefCell::new(Vec::new()),
         }
     }
 
     pub fn get_reciprocal(&self, coords: &Coordinates) -> Coordinates {
-        match self.symmetry {
-            Symmetry::Rotational => {
-                let x = self.world.width - coords.x - 1;
-                let y = self.world.height - coords.y - 1;
-                Coordinates::new(x, y)
-            }
-            _ => todo!(),
-        }
+        self.symmetry.reciprocal(&self.world, coords)
     }
     pub fn get_reciprocal_by_index(&self, i: usize) -> usize {
         let coordinates = self.world.coords_from_index(i);
@@ -49,122 +115,723 @@ impl WorldBuilder {
         return self.world.index_from_coords(&rcp_coords);
     }
 
-    pub fn with_visualiser(mut self, visualiser: Visualiser) -> WorldBuilder {
-        self.visualiser = Some(visualiser);
-        self
+    pub fn lock(&mut self, i: usize) {
+        self.locked[i] = true;
+    }
+    pub fn is_locked(&self, i: usize) -> bool {
+        self.locked[i]
     }
 
-    pub fn fill(mut self, tile_type: TileType) -> WorldBuilder {
-        self.scatter(tile_type, 1.0);
+    pub fn with_visualiser(self, visualiser: Visualiser) -> WorldBuilder {
+        self.with_observer(visualiser)
+    }
+    /// Subscribes `observer` to generation events. Any number of observers
+    /// can be attached and are notified in the order they were added.
+    pub fn with_observer(self, observer: impl GenerationObserver + 'static) -> WorldBuilder {
+        self.observers.borrow_mut().push(Box::new(observer));
         self
     }
+
+    pub fn fill(self, tile_type: TileType) -> WorldBuilder {
+        self.run_step("fill", format!("{:?}", tile_type), |builder| {
+            builder.scatter(tile_type, 1.0)
+        })
+    }
+    pub fn try_fill(self, tile_type: TileType) -> Result<WorldBuilder, GenerationError> {
+        self.try_run_step("fill", format!("{:?}", tile_type), |builder| {
+            builder.scatter(tile_type, 1.0)
+        })
+    }
+    /// Fails the build for three players, as headquarters are placed in
+    /// opposing pairs, or for a count that doesn't match the teams.
     pub fn set_players(mut self, players: Players) -> WorldBuilder {
+        let err = match players {
+            Players::Three => Some(GenerationError::UnpairedPlayers(players)),
+            _ if !self.teams.is_empty() && players != self.teams.players() => {
+                Some(GenerationError::InvalidTeams(self.teams.clone()))
+            }
+            _ => None,
+        };
+        if let Some(err) = err {
+            if self.failure.is_none() {
+                self.failure = Some(err);
+            }
+            return self;
+        }
         self.players = players;
         self
     }
+    /// Runs every step of `preset`'s recipe.
+    pub fn apply_preset(self, preset: Preset) -> WorldBuilder {
+        preset.apply(self)
+    }
+    /// Fails the build if `teams` aren't two equal sides. Teams mirror each
+    /// other, so a map without symmetry becomes rotational.
+    pub fn set_teams(mut self, teams: Teams) -> WorldBuilder {
+        if !teams.is_valid() {
+            if self.failure.is_none() {
+                self.failure = Some(GenerationError::InvalidTeams(teams));
+            }
+            return self;
+        }
+        if self.symmetry == Symmetry::None {
+            log::info!("Teams need a symmetric map, using rotational symmetry");
+            self.symmetry = Symmetry::Rotational;
+        }
+        self.players = teams.players();
+        self.teams = teams;
+        self
+    }
+    /// Keeps the previous symmetry and fails the build if `symmetry` doesn't
+    /// support the map's size, or is `None` while teams are set.
     pub fn set_symmetry(mut self, symmetry: Symmetry) -> WorldBuilder {
+        if let Err(err) = self.check_symmetry(symmetry) {
+            if self.failure.is_none() {
+                self.failure = Some(err);
+            }
+            return self;
+        }
         self.symmetry = symmetry;
         self
     }
-    pub fn create_headquarters(mut self) -> WorldBuilder {
-        // TODO Add player number blocks.
-        let (x, y, r_x, r_y) = loop {
-            let x = rand::thread_rng().gen_range(0..self.world.width);
-            let y = rand::thread_rng().gen_range(0..self.world.height);
-            let Coordinates { x: rx, y: ry } = match self.symmetry {
-                Symmetry::Rotational => self.get_reciprocal(&Coordinates::new(x, y)),
-                _ => todo!(),
-            };
+    pub fn try_set_symmetry(mut self, symmetry: Symmetry) -> Result<WorldBuilder, GenerationError> {
+        self.check_symmetry(symmetry)?;
+        self.symmetry = symmetry;
+        Ok(self)
+    }
+    pub fn set_tileset(mut self, tileset: Tileset) -> WorldBuilder {
+        self.tileset = tileset;
+        self
+    }
+    pub fn set_path_strategy(mut self, path_strategy: PathStrategy) -> WorldBuilder {
+        self.path_strategy = path_strategy;
+        self
+    }
+    pub fn set_hq_constraints(mut self, hq_constraints: HqConstraints) -> WorldBuilder {
+        self.hq_constraints = hq_constraints;
+        self
+    }
+    pub fn set_validation(mut self, validation: ValidationMode) -> WorldBuilder {
+        self.validation = validation;
+        self
+    }
+    /// Copies the non-empty tiles of `template`, mirrored for the symmetry,
+    /// and locks them so later steps generate around them.
+    pub fn apply_template(self, template: &World) -> WorldBuilder {
+        self.run_step("apply_template", String::new(), |builder| {
+            template::apply_template(builder, template)
+        })
+    }
+    pub fn try_apply_template(self, template: &World) -> Result<WorldBuilder, GenerationError> {
+        self.try_run_step("apply_template", String::new(), |builder| {
+            template::apply_template(builder, template)
+        })
+    }
+    /// Places headquarters for every player a template hasn't already.
+    pub fn create_headquarters(self) -> WorldBuilder {
+        self.run_step("create_headquarters", String::new(), |builder| {
+            builder.place_headquarters()
+        })
+    }
+    pub fn try_create_headquarters(self) -> Result<WorldBuilder, GenerationError> {
+        self.try_run_step("create_headquarters", String::new(), |builder| {
+            builder.place_headquarters()
+        })
+    }
+    pub fn join_headquarters(self, tile_type: TileType) -> WorldBuilder {
+        self.run_step("join_headquarters", format!("{:?}", tile_type), |builder| {
+            builder.connect_headquarters(tile_type)
+        })
+    }
+    pub fn try_join_headquarters(
+        self,
+        tile_type: TileType,
+    ) -> Result<WorldBuilder, GenerationError> {
+        self.try_run_step("join_headquarters", format!("{:?}", tile_type), |builder| {
+            builder.connect_headquarters(tile_type)
+        })
+    }
 
-            if rx != x && ry != y {
-                break (x, y, rx, ry);
+    /// Places every city and factory in `budget`, mirrored between players,
+    /// with any missing headquarters first.
+    pub fn allocate_properties(self, budget: PropertyBudget) -> WorldBuilder {
+        self.run_step("allocate_properties", format!("{:?}", budget), |builder| {
+            properties::allocate_properties(builder, &budget)
+        })
+    }
+    pub fn try_allocate_properties(
+        self,
+        budget: PropertyBudget,
+    ) -> Result<WorldBuilder, GenerationError> {
+        self.try_run_step("allocate_properties", format!("{:?}", budget), |builder| {
+            properties::allocate_properties(builder, &budget)
+        })
+    }
+    pub fn create_roads(mut self) -> WorldBuilder {
+        log::warn!("create_roads is not yet implemented");
+        self
+    }
+    pub fn add_seas(mut self) -> WorldBuilder {
+        log::warn!("add_seas is not yet implemented");
+        self
+    }
+    pub fn add_forests(self, density: f32) -> WorldBuilder {
+        self.run_step("add_forests", format!("density={}", density), |builder| {
+            builder.scatter(TileType::Forest, density)
+        })
+    }
+    pub fn try_add_forests(self, density: f32) -> Result<WorldBuilder, GenerationError> {
+        self.try_run_step("add_forests", format!("density={}", density), |builder| {
+            builder.scatter(TileType::Forest, density)
+        })
+    }
+    pub fn add_mountains(self, density: f32) -> WorldBuilder {
+        self.run_step("add_mountains", format!("density={}", density), |builder| {
+            builder.scatter(TileType::Mountain, density)
+        })
+    }
+    pub fn try_add_mountains(self, density: f32) -> Result<WorldBuilder, GenerationError> {
+        self.try_run_step("add_mountains", format!("density={}", density), |builder| {
+            builder.scatter(TileType::Mountain, density)
+        })
+    }
+    pub fn cluster_terrain(self, config: ClusterConfig) -> WorldBuilder {
+        self.run_step("cluster_terrain", format!("{:?}", config), |builder| {
+            for _ in 0..config.passes {
+                cluster::smooth(builder, &config);
             }
+            Ok(())
+        })
+    }
+    pub fn add_noise_terrain(self, config: NoiseConfig) -> WorldBuilder {
+        self.run_step("add_noise_terrain", format!("{:?}", config), |builder| {
+            let fields = TerrainFields::generate(builder, &config);
+            for i in 0..builder.world.tiles.len() {
+                if builder.world.tile_at(i).is_empty() {
+                    builder
+                        .world
+                        .update_tile_by_index(i, fields.tile_at(i, &config));
+                }
+            }
+            builder.terrain_fields = Some(fields);
+            Ok(())
+        })
+    }
+    /// Converts terrain until plains, forest, mountain and sea make up the
+    /// budgeted share of the whole map. Run it after the other terrain steps,
+    /// as they add to whatever is already there.
+    pub fn apply_terrain_budget(self, budget: TerrainBudget) -> WorldBuilder {
+        self.run_step("apply_terrain_budget", format!("{:?}", budget), |builder| {
+            terrain::apply_budget(builder, &budget)
+        })
+    }
+    pub fn try_apply_terrain_budget(
+        self,
+        budget: TerrainBudget,
+    ) -> Result<WorldBuilder, GenerationError> {
+        self.try_run_step("apply_terrain_budget", format!("{:?}", budget), |builder| {
+            terrain::apply_budget(builder, &budget)
+        })
+    }
+    /// Adds wasteland, ruins, hazards and meteors. Needs the Days of Ruin
+    /// tileset.
+    pub fn add_ruin_terrain(self, config: RuinConfig) -> WorldBuilder {
+        self.run_step("add_ruin_terrain", format!("{:?}", config), |builder| {
+            builder.check_tileset(TileType::Wasteland)?;
+            ruins::add_ruin_terrain(builder, &config)
+        })
+    }
+    pub fn try_add_ruin_terrain(self, config: RuinConfig) -> Result<WorldBuilder, GenerationError> {
+        self.try_run_step("add_ruin_terrain", format!("{:?}", config), |builder| {
+            builder.check_tileset(TileType::Wasteland)?;
+            ruins::add_ruin_terrain(builder, &config)
+        })
+    }
+    /// Places `count` neutral com towers, and their reciprocals, on the most
+    /// contested tiles.
+    pub fn add_com_towers(self, count: u32) -> WorldBuilder {
+        self.run_step("add_com_towers", format!("count={}", count), |builder| {
+            contested::place_contested(builder, TileType::ComTower(None), count, MovementType::Foot)
+        })
+    }
+    pub fn try_add_com_towers(self, count: u32) -> Result<WorldBuilder, GenerationError> {
+        self.try_run_step("add_com_towers", format!("count={}", count), |builder| {
+            contested::place_contested(builder, TileType::ComTower(None), count, MovementType::Foot)
+        })
+    }
+    /// Places `count` neutral labs, and their reciprocals, on the most
+    /// contested tiles.
+    pub fn add_labs(self, count: u32) -> WorldBuilder {
+        self.run_step("add_labs", format!("count={}", count), |builder| {
+            contested::place_contested(builder, TileType::Lab(None), count, MovementType::Foot)
+        })
+    }
+    pub fn try_add_labs(self, count: u32) -> Result<WorldBuilder, GenerationError> {
+        self.try_run_step("add_labs", format!("count={}", count), |builder| {
+            contested::place_contested(builder, TileType::Lab(None), count, MovementType::Foot)
+        })
+    }
+    /// Walls off opposing headquarters from each other with pipes, leaving
+    /// `seams` breakable points on each side.
+    pub fn add_pipe_walls(self, seams: u32) -> WorldBuilder {
+        self.run_step("add_pipe_walls", format!("seams={}", seams), |builder| {
+            builder.check_tileset(TileType::Pipe)?;
+            pipes::add_pipe_walls(builder, seams)
+        })
+    }
+    pub fn try_add_pipe_walls(self, seams: u32) -> Result<WorldBuilder, GenerationError> {
+        self.try_run_step("add_pipe_walls", format!("seams={}", seams), |builder| {
+            builder.check_tileset(TileType::Pipe)?;
+            pipes::add_pipe_walls(builder, seams)
+        })
+    }
+    /// Deploys a starting army around each headquarters. Run this last, as
+    /// later steps don't move units off tiles they make impassable.
+    pub fn deploy_units(self, config: DeployConfig) -> WorldBuilder {
+        self.run_step("deploy_units", format!("{:?}", config), |builder| {
+            units::deploy_units(builder, &config)
+        })
+    }
+    pub fn try_deploy_units(self, config: DeployConfig) -> Result<WorldBuilder, GenerationError> {
+        self.try_run_step("deploy_units", format!("{:?}", config), |builder| {
+            units::deploy_units(builder, &config)
+        })
+    }
+    /// Collapses empty tiles with `model`. If every attempt contradicts
+    /// itself the conflicting rules are relaxed and a warning is recorded.
+    pub fn fill_wfc(self, model: &WfcModel) -> WorldBuilder {
+        self.run_step("fill_wfc", String::new(), |builder| {
+            builder.collapse_wfc(model)
+        })
+    }
+    pub fn try_fill_wfc(self, model: &WfcModel) -> Result<WorldBuilder, GenerationError> {
+        self.try_run_step("fill_wfc", String::new(), |builder| {
+            builder.collapse_wfc(model)
+        })
+    }
+    /// Compensates the worst-off player until per-player metrics are within
+    /// `tolerance`. Meant for maps generated with `Symmetry::None`.
+    pub fn balance(self, tolerance: f32) -> WorldBuilder {
+        self.run_step("balance", format!("tolerance={}", tolerance), |builder| {
+            fairness::balance(builder, tolerance, MovementType::Foot)
+        })
+    }
+    pub fn try_balance(self, tolerance: f32) -> Result<WorldBuilder, GenerationError> {
+        self.try_run_step("balance", format!("tolerance={}", tolerance), |builder| {
+            fairness::balance(builder, tolerance, MovementType::Foot)
+        })
+    }
+    pub fn build(self) -> Result<World, GenerationError> {
+        self.build_with_warnings().map(|(world, _)| world)
+    }
+    pub fn build_with_warnings(self) -> Result<(World, Vec<GenerationError>), GenerationError> {
+        let (result, _) = self.build_with_trace();
+        result
+    }
+    /// Builds the world and returns the trace of every step, whether or not
+    /// generation succeeded.
+    pub fn build_with_trace(
+        mut self,
+    ) -> (
+        Result<(World, Vec<GenerationError>), GenerationError>,
+        GenerationTrace,
+    ) {
+        let validation = self.validation;
+        let result = match self.failure.take() {
+            Some(err) => Err(err),
+            None if validation == ValidationMode::Off => Ok(()),
+            None => self.traced("validate", format!("{:?}", validation), |builder| {
+                let report = match validation {
+                    ValidationMode::Repair => validation::repair(builder, MovementType::Foot),
+                    _ => validation::validate(&builder.world, MovementType::Foot),
+                };
+                if !report.is_valid() {
+                    return Err(GenerationError::InvalidMap(report));
+                }
+                Ok(())
+            }),
         };
+        if result.is_ok() {
+            self.notify(|observer| observer.on_complete(&self.world));
+        }
+        self.trace.terrain = self.terrain_report();
+        let result = result.map(|_| (self.world, self.warnings));
+        (result, self.trace)
+    }
 
-        let id = self.world.index_from_coords(&Coordinates::new(x, y));
-        let r_id = self.world.index_from_coords(&Coordinates::new(r_x, r_y));
-        self.world.tiles[id] = TileType::Hq(Player::One);
-        self.world.tiles[r_id] = TileType::Hq(Player::Two);
+    /// How the map's terrain compares with the last budget applied, if any.
+    /// `build_with_trace` includes the report for the finished map.
+    pub fn terrain_report(&self) -> Option<TerrainReport> {
+        self.terrain_budget.map(|budget| budget.report(&self.world))
+    }
+
+    pub(crate) fn notify(&self, mut event: impl FnMut(&mut dyn GenerationObserver)) {
+        for observer in self.observers.borrow_mut().iter_mut() {
+            event(observer.as_mut());
+        }
+    }
 
+    /// Counts a retry against the step currently running.
+    pub(crate) fn record_retry(&self) {
+        self.retries.set(self.retries.get() + 1);
+    }
+
+    fn run_step(
+        mut self,
+        name: &'static str,
+        parameters: String,
+        step: impl FnOnce(&mut WorldBuilder) -> Result<(), GenerationError>,
+    ) -> WorldBuilder {
+        if let Err(err) = self.traced(name, parameters, step) {
+            if err.is_recoverable() {
+                self.warnings.push(err);
+            } else if self.failure.is_none() {
+                self.failure = Some(err);
+            }
+        }
         self
     }
-    pub fn join_headquarters(mut self, tile_type: TileType) -> WorldBuilder {
-        let hq_locations = self.world.find_headquarters();
 
-        if hq_locations.len() == 0 {
-            println!("WARNING: Couldn't find headquarters. Continuing...");
-            return self;
+    fn try_run_step(
+        mut self,
+        name: &'static str,
+        parameters: String,
+        step: impl FnOnce(&mut WorldBuilder) -> Result<(), GenerationError>,
+    ) -> Result<WorldBuilder, GenerationError> {
+        self.traced(name, parameters, step)?;
+        Ok(self)
+    }
+
+    fn traced(
+        &mut self,
+        name: &'static str,
+        parameters: String,
+        step: impl FnOnce(&mut WorldBuilder) -> Result<(), GenerationError>,
+    ) -> Result<(), GenerationError> {
+        log::info!("Running {}({})", name, parameters);
+        let before = self.world.tiles.clone();
+        self.retries.set(0);
+        let start = Instant::now();
+        self.notify(|observer| observer.on_step_start(name, &self.world));
+
+        let result = step(self);
+        self.notify(|observer| observer.on_step_end(name, &self.world));
+
+        let record = StepRecord {
+            name,
+            parameters,
+            tiles_changed: before
+                .iter()
+                .zip(self.world.tiles.iter())
+                .filter(|(a, b)| a != b)
+                .count(),
+            retries: self.retries.get(),
+            elapsed_ms: start.elapsed().as_secs_f64() * 1000.0,
+            error: result.as_ref().err().map(ToString::to_string),
+        };
+        match &result {
+            Ok(()) => log::debug!("{:?}", record),
+            Err(err) => log::warn!("{} failed: {}", name, err),
         }
+        self.trace.steps.push(record);
+        result
+    }
 
-        let hq_1 = hq_locations[0];
-        let hq_2 = hq_locations[1];
-        let path = TilePath::generate(hq_1, hq_2, self.symmetry, &self, tile_type);
+    /// Places headquarters for every player who doesn't have one yet, keeping
+    /// any that a template already put on the map. Each player's opposite
+    /// number goes at the reciprocal of their headquarters, or within a few
+    /// tiles of it without symmetry, and teammates within a few tiles of
+    /// each other.
+    fn place_headquarters(&mut self) -> Result<(), GenerationError> {
+        let pairs = self.player_pairs();
+        let existing: Vec<(Player, Coordinates)> = self
+            .world
+            .find_headquarters()
+            .into_iter()
+            .filter_map(|hq| Some((self.world.tile_at_coords(&hq).owner()?, hq)))
+            .collect();
+        let find = |hqs: &[(Player, Coordinates)], player: Player| {
+            hqs.iter()
+                .find(|(owner, _)| *owner == player)
+                .map(|(_, hq)| *hq)
+        };
+        let complete = pairs.iter().all(|&(player, counterpart)| {
+            find(&existing, player).is_some() && find(&existing, counterpart).is_some()
+        });
+        if complete {
+            log::info!("Keeping the headquarters already on the map");
+            return Ok(());
+        }
 
-        match path {
-            Ok(path) => {
-                let all_tiles: Vec<_> =
-                    path.tiles.iter().zip(path.reciprocal_path.iter()).collect();
-                for (tile, reciprocal) in all_tiles {
-                    self.world.update_tile(&tile, tile_type.clone());
-                    self.world.update_tile(&reciprocal, tile_type.clone());
-                }
-            }
-            Err(err) => match err {
-                PathGenerationError::InfiniteLoopErr(tiles, reciprocals)
-                | PathGenerationError::NoNextTileErr(tiles, reciprocals) => {
-                    println!("Error generating path");
-                    let all_tiles: Vec<_> = tiles.iter().zip(reciprocals.iter()).collect();
-                    for (tile, reciprocal) in all_tiles {
-                        self.world.update_tile(&tile, TileType::Road);
-                        self.world.update_tile(&reciprocal, TileType::Road);
+        let mut placement = None;
+        for _ in 0..self.hq_constraints.max_attempts {
+            let mut hqs = existing.clone();
+            for &(player, counterpart) in &pairs {
+                let hq = match (find(&hqs, player), find(&hqs, counterpart)) {
+                    (Some(hq), _) => hq,
+                    (None, Some(rcp)) if self.symmetry != Symmetry::None => {
+                        self.get_reciprocal(&rcp)
                     }
+                    _ => match self.teammate(player).and_then(|mate| find(&hqs, mate)) {
+                        Some(mate) => self.random_coords_near(&mate, TEAMMATE_SPREAD),
+                        None => self.random_coords(),
+                    },
+                };
+                if find(&hqs, player).is_none() {
+                    hqs.push((player, hq));
                 }
-            },
+                if find(&hqs, counterpart).is_none() {
+                    let rcp = match self.symmetry {
+                        Symmetry::None => self.random_coords_near(
+                            &Symmetry::Rotational.reciprocal(&self.world, &hq),
+                            COUNTERPART_SPREAD,
+                        ),
+                        _ => self.get_reciprocal(&hq),
+                    };
+                    hqs.push((counterpart, rcp));
+                }
+            }
+            if self.headquarters_allowed(&hqs[existing.len()..], &hqs, &pairs) {
+                placement = Some(hqs.split_off(existing.len()));
+                break;
+            }
+            self.record_retry();
         }
-        self
+        let Some(placed) = placement else {
+            return Err(GenerationError::HeadquartersPlacement(
+                self.hq_constraints.max_attempts,
+            ));
+        };
+
+        for (player, coords) in placed {
+            let i = self.world.try_index_from_coords(&coords)?;
+            self.world.tiles[i] = TileType::Hq(player);
+            self.lock(i);
+        }
+        Ok(())
     }
 
-    pub fn create_team_cities(mut self) -> WorldBuilder {
-        println!("Not yet implemented");
-        self
+    /// Whether the newly `placed` headquarters can join the rest of `hqs`:
+    /// none share or touch a tile, each pair of opposite numbers is far
+    /// enough apart for the constraints, teammates are nearer each other
+    /// than any enemy, and territory is split fairly.
+    fn headquarters_allowed(
+        &self,
+        placed: &[(Player, Coordinates)],
+        hqs: &[(Player, Coordinates)],
+        pairs: &[(Player, Player)],
+    ) -> bool {
+        let coords_of = |player: Player| {
+            hqs.iter()
+                .find(|(owner, _)| *owner == player)
+                .map(|(_, hq)| *hq)
+        };
+        let is_new = |player: Player| placed.iter().any(|(owner, _)| *owner == player);
+
+        let spaced = placed.iter().all(|(player, hq)| {
+            self.is_unlocked(hq)
+                && hqs
+                    .iter()
+                    .filter(|(other, _)| other != player)
+                    .all(|(_, other)| hq != other && !hq.neighbours(other))
+        });
+        let allowed = pairs.iter().all(|&(player, counterpart)| {
+            if !is_new(player) && !is_new(counterpart) {
+                return true;
+            }
+            let (Some(hq), Some(rcp)) = (coords_of(player), coords_of(counterpart)) else {
+                return false;
+            };
+            self.headquarters_apart(&hq, &rcp) && self.hq_constraints.allows(&self.world, &hq, &rcp)
+        });
+        let grouped = placed.iter().all(|(player, hq)| {
+            let Some(team) = self.teams.team_of(*player) else {
+                return true;
+            };
+            let nearest = |same_team: bool| {
+                hqs.iter()
+                    .filter(|(other, coords)| {
+                        coords != hq && (self.teams.team_of(*other) == Some(team)) == same_team
+                    })
+                    .map(|(_, coords)| hq.distance(coords))
+                    .fold(f32::INFINITY, f32::min)
+            };
+            self.teams.0[team].len() == 1 || nearest(true) < nearest(false)
+        });
+        spaced && allowed && grouped && self.territory_balanced(hqs)
     }
-    pub fn create_neutral_cities(mut self) -> WorldBuilder {
-        println!("Not yet implemented");
-        self
+
+    /// Without symmetry nothing else evens out where headquarters start, so
+    /// their territory on the map so far has to be within tolerance.
+    fn territory_balanced(&self, hqs: &[(Player, Coordinates)]) -> bool {
+        if self.symmetry != Symmetry::None {
+            return true;
+        }
+        let mut world = self.world.clone();
+        for (player, coords) in hqs {
+            world.update_tile(coords, TileType::Hq(*player));
+        }
+        fairness::analyse(&world, MovementType::Foot)
+            .by_team(&self.teams)
+            .worst_off(
+                fairness::Metric::Territory,
+                self.hq_constraints.territory_tolerance,
+            )
+            .is_none()
     }
-    pub fn create_team_factories(mut self) -> WorldBuilder {
-        println!("Not yet implemented");
-        self
+
+    /// Each player and their opposite number: one from each team, or One
+    /// against Two and Three against Four without teams.
+    pub(crate) fn player_pairs(&self) -> Vec<(Player, Player)> {
+        match (&self.teams.0[..], self.players) {
+            ([first, second], _) => first.iter().copied().zip(second.iter().copied()).collect(),
+            (_, Players::Four) => vec![(Player::One, Player::Two), (Player::Three, Player::Four)],
+            _ => vec![(Player::One, Player::Two)],
+        }
     }
-    pub fn create_neutral_factories(mut self) -> WorldBuilder {
-        println!("Not yet implemented");
-        self
+
+    /// The player `player` shares a team with, if any.
+    fn teammate(&self, player: Player) -> Option<Player> {
+        let team = &self.teams.0[self.teams.team_of(player)?];
+        team.iter().copied().find(|other| *other != player)
     }
-    pub fn create_roads(mut self) -> WorldBuilder {
-        println!("Not yet implemented");
-        self
+
+    /// The headquarters that the main road joins: the first player and their
+    /// opposite number, or without teams the first two on the map when those
+    /// players have none.
+    pub(crate) fn opposing_headquarters(&self) -> Option<(Coordinates, Coordinates)> {
+        let hq_locations = self.world.find_headquarters();
+        let find = |player: Player| {
+            hq_locations
+                .iter()
+                .copied()
+                .find(|hq| self.world.tile_at_coords(hq).owner() == Some(player))
+        };
+        let (player, counterpart) = self.player_pairs()[0];
+        match (find(player), find(counterpart), &hq_locations[..]) {
+            (Some(hq_1), Some(hq_2), _) => Some((hq_1, hq_2)),
+            (_, _, [hq_1, hq_2, ..]) if self.teams.is_empty() => Some((*hq_1, *hq_2)),
+            _ => None,
+        }
     }
-    pub fn add_seas(mut self) -> WorldBuilder {
-        println!("Not yet implemented");
-        self
+
+    fn check_tileset(&self, tile_type: TileType) -> Result<(), GenerationError> {
+        if !self.tileset.contains(&tile_type) {
+            return Err(GenerationError::UnsupportedTile {
+                tile_type,
+                tileset: self.tileset,
+            });
+        }
+        Ok(())
     }
-    pub fn add_forests(mut self, density: f32) -> WorldBuilder {
-        self.scatter(TileType::Forest, density);
-        self
+
+    fn check_symmetry(&self, symmetry: Symmetry) -> Result<(), GenerationError> {
+        if symmetry == Symmetry::None && !self.teams.is_empty() {
+            return Err(GenerationError::TeamsNeedSymmetry);
+        }
+        let (width, height) = (self.world.width, self.world.height);
+        if !symmetry.supports(width, height) {
+            return Err(GenerationError::UnsupportedSymmetry {
+                symmetry,
+                width,
+                height,
+            });
+        }
+        Ok(())
     }
-    pub fn add_mountains(mut self, density: f32) -> WorldBuilder {
-        self.scatter(TileType::Mountain, density);
-        self
+
+    fn is_unlocked(&self, coords: &Coordinates) -> bool {
+        !self.is_locked(self.world.index_from_coords(coords))
     }
-    pub fn build(self) -> World {
-        self.world
+
+    fn random_coords(&self) -> Coordinates {
+        let x = rand::thread_rng().gen_range(0..self.world.width);
+        let y = rand::thread_rng().gen_range(0..self.world.height);
+        Coordinates::new(x, y)
+    }
+
+    /// Random coordinates up to `spread` tiles away from `coords` on each axis.
+    fn random_coords_near(&self, coords: &Coordinates, spread: u32) -> Coordinates {
+        let range = |centre: u32, size: u32| {
+            let low = centre.saturating_sub(spread);
+            let high = (centre + spread).min(size - 1);
+            rand::thread_rng().gen_range(low..=high)
+        };
+        Coordinates::new(
+            range(coords.x, self.world.width),
+            range(coords.y, self.world.height),
+        )
     }
 
-    fn scatter(&mut self, scatter_type: TileType, density: f32) {
-        if density > 1.0 {
-            println!("Density must be between 0.0 and 1.0. Skipping...");
-            return;
+    /// Rotated headquarters may not share a row or column. Mirrored ones
+    /// always do, so they only have to be off the mirror line and not
+    /// touching, as do headquarters placed without symmetry.
+    fn headquarters_apart(&self, hq: &Coordinates, rcp: &Coordinates) -> bool {
+        match self.symmetry {
+            Symmetry::Rotational => hq.x != rcp.x && hq.y != rcp.y,
+            Symmetry::Horizontal
+            | Symmetry::Vertical
+            | Symmetry::Diagonal
+            | Symmetry::AntiDiagonal
+            | Symmetry::None => hq != rcp && !hq.neighbours(rcp),
         }
+    }
+
+    fn connect_headquarters(&mut self, tile_type: TileType) -> Result<(), GenerationError> {
+        let Some((hq_1, hq_2)) = self.opposing_headquarters() else {
+            return Err(GenerationError::MissingHeadquarters);
+        };
+        let path = match self.path_strategy {
+            PathStrategy::RandomWalk => {
+                TilePath::generate(hq_1, hq_2, self.symmetry, self, tile_type)
+            }
+            PathStrategy::Shortest { noise } => {
+                TilePath::generate_shortest(hq_1, hq_2, self.symmetry, self, tile_type, noise)
+            }
+        };
+
+        let (tiles, reciprocals, result) = match path {
+            Ok(path) => (path.tiles, path.reciprocal_path, Ok(())),
+            Err(err) => match err {
+                PathGenerationError::InfiniteLoopErr(tiles, reciprocals)
+                | PathGenerationError::NoNextTileErr(tiles, reciprocals) => {
+                    (tiles, reciprocals, Err(GenerationError::IncompletePath))
+                }
+                PathGenerationError::NoRoute => return Err(GenerationError::NoRoute),
+            },
+        };
+        for (tile, reciprocal) in tiles.iter().zip(reciprocals.iter()) {
+            self.world.try_update_tile(tile, tile_type)?;
+            self.world.try_update_tile(reciprocal, tile_type)?;
+            self.lock_coords(tile);
+            self.lock_coords(reciprocal);
+        }
+        result
+    }
+
+    fn collapse_wfc(&mut self, model: &WfcModel) -> Result<(), GenerationError> {
+        match model.collapse(self) {
+            Ok(tiles) => {
+                self.world.tiles = tiles;
+                Ok(())
+            }
+            Err(err) => {
+                self.world.tiles = model.collapse_relaxed(self);
+                Err(err)
+            }
+        }
+    }
+
+    fn lock_coords(&mut self, coords: &Coordinates) {
+        let i = self.world.index_from_coords(coords);
+        self.lock(i);
+    }
+
+    fn scatter(&mut self, scatter_type: TileType, density: f32) -> Result<(), GenerationError> {
+        if !(0.0..=1.0).contains(&density) {
+            return Err(GenerationError::InvalidDensity(density));
+        }
+        self.check_tileset(scatter_type)?;
 
         for i in 0..self.world.tiles.len() {
             let rcp_i = self.get_reciprocal_by_index(i);
@@ -178,5 +845,270 @@ impl WorldBuilder {
                 }
             }
         }
+        Ok(())
+    }
+}
+
+#[test]
+fn test_observers_receive_every_step() {
+    use std::rc::Rc;
+    use visualiser::Recorder;
+
+    let first = Rc::new(RefCell::new(Recorder::new()));
+    let second = Rc::new(RefCell::new(Recorder::new()));
+    WorldBuilder::new(Config {
+        height: 6,
+        width: 6,
+    })
+    .with_observer(first.clone())
+    .with_observer(second.clone())
+    .create_headquarters()
+    .fill(TileType::Plains)
+    .build()
+    .unwrap();
+
+    for recorder in [first, second] {
+        let steps: Vec<_> = recorder
+            .borrow()
+            .frames
+            .iter()
+            .map(|frame| frame.step.clone())
+            .collect();
+        assert_eq!(steps, ["create_headquarters", "fill"]);
+    }
+}
+
+#[test]
+fn test_headquarters_respect_constraints() {
+    use headquarters::Quadrant;
+
+    let constraints = HqConstraints {
+        min_separation: Some(8),
+        max_separation: Some(12),
+        edge_margin: 2,
+        quadrants: vec![Quadrant::TopLeft],
+        ..HqConstraints::default()
+    };
+    for _ in 0..20 {
+        let world = WorldBuilder::new(Config {
+            height: 15,
+            width: 15,
+        })
+        .set_hq_constraints(constraints.clone())
+        .try_create_headquarters()
+        .unwrap()
+        .world;
+        let hqs = world.find_headquarters();
+        assert_eq!(hqs.len(), 2);
+        assert!(constraints.allows(&world, &hqs[0], &hqs[1]));
+        assert_eq!(Quadrant::of(&world, &hqs[0]), Quadrant::TopLeft);
+    }
+}
+
+#[test]
+fn test_impossible_headquarters_constraints_fail() {
+    let result = WorldBuilder::new(Config {
+        height: 6,
+        width: 6,
+    })
+    .set_hq_constraints(HqConstraints {
+        min_separation: Some(20),
+        max_attempts: 50,
+        ..HqConstraints::default()
+    })
+    .create_headquarters()
+    .build();
+    assert!(matches!(
+        result,
+        Err(GenerationError::HeadquartersPlacement(50))
+    ));
+}
+
+#[test]
+fn test_four_players_without_teams() {
+    for symmetry in [Symmetry::Rotational, Symmetry::None] {
+        let world = WorldBuilder::new(Config {
+            height: 15,
+            width: 15,
+        })
+        .set_players(Players::Four)
+        .set_symmetry(symmetry)
+        .create_headquarters()
+        .allocate_properties(properties::PropertyBudget {
+            cities: 2,
+            factories: 1,
+            neutral_cities: 1,
+            neutral_factories: 0,
+        })
+        .fill(TileType::Plains)
+        .build()
+        .unwrap();
+        for player in [Player::One, Player::Two, Player::Three, Player::Four] {
+            let owned =
+                |tile_type: TileType| world.tiles.iter().filter(|t| **t == tile_type).count();
+            assert_eq!(owned(TileType::Hq(player)), 1);
+            assert_eq!(owned(TileType::City(Some(player))), 2);
+            assert_eq!(owned(TileType::Factory(Some(player))), 1);
+        }
+    }
+
+    let result = WorldBuilder::new(Config {
+        height: 15,
+        width: 15,
+    })
+    .set_players(Players::Three)
+    .create_headquarters()
+    .build();
+    assert!(matches!(
+        result,
+        Err(GenerationError::UnpairedPlayers(Players::Three))
+    ));
+}
+
+#[cfg(test)]
+fn assert_symmetric(world: &World, symmetry: Symmetry, step: &str) {
+    use std::mem::discriminant;
+
+    for i in 0..world.tiles.len() {
+        let coords = world.coords_from_index(i);
+        let rcp = symmetry.reciprocal(world, &coords);
+        // Properties mirror each other with different owners.
+        assert_eq!(
+            discriminant(world.tile_at(i)),
+            discriminant(world.tile_at_coords(&rcp)),
+            "{:?} {}x{} broken by {} at {:?}",
+            symmetry,
+            world.width,
+            world.height,
+            step,
+            coords
+        );
+    }
+}
+
+#[test]
+fn test_every_step_preserves_symmetry() {
+    use std::rc::Rc;
+    use visualiser::Recorder;
+
+    let model = WfcModel::from_examples();
+    let sizes = [(3, 3), (4, 3), (3, 7), (5, 8), (9, 6), (12, 7), (10, 10)];
+    let square_sizes = [(3, 3), (4, 4), (7, 7), (10, 10)];
+    for (symmetry, sizes) in [
+        (Symmetry::Rotational, &sizes[..]),
+        (Symmetry::Horizontal, &sizes[..]),
+        (Symmetry::Vertical, &sizes[..]),
+        (Symmetry::Diagonal, &square_sizes[..]),
+        (Symmetry::AntiDiagonal, &square_sizes[..]),
+    ] {
+        for &(width, height) in sizes {
+            let recorder = Rc::new(RefCell::new(Recorder::new()));
+            let builder = || {
+                WorldBuilder::new(Config { height, width })
+                    .with_observer(recorder.clone())
+                    .set_symmetry(symmetry)
+                    .set_validation(ValidationMode::Repair)
+                    .create_headquarters()
+            };
+            let failed = |err| panic!("{:?} {}x{}: {}", symmetry, width, height, err);
+            builder()
+                .join_headquarters(TileType::Road)
+                .add_forests(0.1)
+                .add_mountains(0.1)
+                .cluster_terrain(ClusterConfig::default())
+                .add_noise_terrain(NoiseConfig::default())
+                .fill(TileType::Plains)
+                .build()
+                .unwrap_or_else(failed);
+            builder()
+                .set_path_strategy(PathStrategy::Shortest { noise: 0.5 })
+                .join_headquarters(TileType::Road)
+                .fill_wfc(&model)
+                .build()
+                .unwrap_or_else(failed);
+
+            for frame in recorder.borrow().frames.iter() {
+                assert_symmetric(&frame.world, symmetry, &frame.step);
+            }
+        }
+    }
+}
+
+#[test]
+fn test_diagonal_symmetry_needs_square_map() {
+    let builder = WorldBuilder::new(Config {
+        height: 6,
+        width: 8,
+    });
+    assert!(matches!(
+        builder.try_set_symmetry(Symmetry::Diagonal),
+        Err(GenerationError::UnsupportedSymmetry { .. })
+    ));
+
+    let result = WorldBuilder::new(Config {
+        height: 6,
+        width: 8,
+    })
+    .set_symmetry(Symmetry::AntiDiagonal)
+    .create_headquarters()
+    .fill(TileType::Plains)
+    .build();
+    assert!(matches!(
+        result,
+        Err(GenerationError::UnsupportedSymmetry { .. })
+    ));
+}
+
+#[test]
+fn test_asymmetric_generation() {
+    let runs = 20;
+    let mut balanced = 0;
+    for _ in 0..runs {
+        let result = WorldBuilder::new(Config {
+            height: 12,
+            width: 16,
+        })
+        .set_symmetry(Symmetry::None)
+        .set_validation(ValidationMode::Repair)
+        .create_headquarters()
+        .join_headquarters(TileType::Road)
+        .add_noise_terrain(NoiseConfig::default())
+        .balance(0.2)
+        .build_with_warnings();
+        let (world, warnings) = result.unwrap();
+        assert_eq!(world.find_headquarters().len(), 2);
+        for warning in warnings {
+            assert!(matches!(warning, GenerationError::Unbalanced(_)));
+        }
+        if fairness::analyse(&world, MovementType::Foot).is_balanced(0.2) {
+            balanced += 1;
+        }
+    }
+    // Balancing is best effort, so a few maps may stay out of tolerance.
+    assert!(
+        balanced * 10 >= runs * 8,
+        "{} of {} balanced",
+        balanced,
+        runs
+    );
+}
+
+#[test]
+fn test_asymmetric_headquarters_split_territory() {
+    for _ in 0..20 {
+        let builder = WorldBuilder::new(Config {
+            height: 12,
+            width: 16,
+        })
+        .set_symmetry(Symmetry::None)
+        .try_create_headquarters()
+        .unwrap();
+        let report = fairness::analyse(&builder.world, MovementType::Foot);
+        assert_eq!(
+            report.worst_off(fairness::Metric::Territory, 0.1),
+            None,
+            "{:?}",
+            report.players
+        );
     }
 }
@@ -0,0 +1,174 @@
+//! Fractal value noise for terrain generation.
+
+use rand::Rng;
+use world_core::TileType;
+
+use crate::WorldBuilder;
+
+/// Settings for `WorldBuilder::add_noise_terrain`.
+///
+/// Both fields are normalised to `0.0..=1.0`. Tiles below `sea_level` become
+/// sea, below `shoal_level` shoal and above `mountain_level` mountain. The
+/// remaining land is forest where moisture exceeds `forest_moisture`, and
+/// plains otherwise.
+#[derive(Debug)]
+pub struct NoiseConfig {
+    /// Size of the coarsest noise feature, in tiles.
+    pub scale: f32,
+    pub octaves: u32,
+    /// Amplitude multiplier applied to each successive octave.
+    pub persistence: f32,
+    pub sea_level: f32,
+    pub shoal_level: f32,
+    pub mountain_level: f32,
+    pub forest_moisture: f32,
+}
+
+impl Default for NoiseConfig {
+    fn default() -> NoiseConfig {
+        NoiseConfig {
+            scale: 8.0,
+            octaves: 3,
+            persistence: 0.5,
+            sea_level: 0.2,
+            shoal_level: 0.26,
+            mountain_level: 0.78,
+            forest_moisture: 0.65,
+        }
+    }
+}
+
+/// Per-tile scalar fields, indexed like `World::tiles`.
+#[derive(Clone)]
+pub struct TerrainFields {
+    pub elevation: Vec<f32>,
+    pub moisture: Vec<f32>,
+}
+
+impl TerrainFields {
+    pub fn generate(builder: &WorldBuilder, config: &NoiseConfig) -> TerrainFields {
+        let (width, height) = (builder.world.width, builder.world.height);
+        let elevation = fractal_noise(
+            width,
+            height,
+            config.scale,
+            config.octaves,
+            config.persistence,
+        );
+        let moisture = fractal_noise(
+            width,
+            height,
+            config.scale,
+            config.octaves,
+            config.persistence,
+        );
+        TerrainFields {
+            elevation: symmetrise(builder, elevation),
+            moisture: symmetrise(builder, moisture),
+        }
+    }
+
+    pub fn tile_at(&self, i: usize, config: &NoiseConfig) -> TileType {
+        let elevation = self.elevation[i];
+        if elevation < config.sea_level {
+            TileType::Sea
+        } else if elevation < config.shoal_level {
+            TileType::Shoal
+        } else if elevation > config.mountain_level {
+            TileType::Mountain
+        } else if self.moisture[i] > config.forest_moisture {
+            TileType::Forest
+        } else {
+            TileType::Plains
+        }
+    }
+}
+
+/// Sums `octaves` layers of value noise, each at twice the frequency of the
+/// last, and normalises the result to `0.0..=1.0`.
+pub fn fractal_noise(
+    width: u32,
+    height: u32,
+    scale: f32,
+    octaves: u32,
+    persistence: f32,
+) -> Vec<f32> {
+    let mut field = vec![0.0; (width * height) as usize];
+    let mut amplitude = 1.0;
+    let mut frequency = 1.0 / scale.max(1.0);
+    for _ in 0..octaves.max(1) {
+        let octave = value_noise(width, height, frequency);
+        for (value, noise) in field.iter_mut().zip(octave) {
+            *value += noise * amplitude;
+        }
+        amplitude *= persistence;
+        frequency *= 2.0;
+    }
+    normalise(field)
+}
+
+/// Random values on a lattice with spacing `1 / frequency` tiles, smoothly
+/// interpolated at every tile.
+fn value_noise(width: u32, height: u32, frequency: f32) -> Vec<f32> {
+    let lattice_width = (width as f32 * frequency).ceil() as usize + 2;
+    let lattice_height = (height as f32 * frequency).ceil() as usize + 2;
+    let mut rng = rand::thread_rng();
+    let lattice: Vec<f32> = (0..lattice_width * lattice_height)
+        .map(|_| rng.gen_range(0.0..1.0))
+        .collect();
+    let at = |x: usize, y: usize| lattice[y * lattice_width + x];
+
+    let mut field = Vec::with_capacity((width * height) as usize);
+    for y in 0..height {
+        for x in 0..width {
+            let fx = x as f32 * frequency;
+            let fy = y as f32 * frequency;
+            let (x0, y0) = (fx.floor() as usize, fy.floor() as usize);
+            let tx = smoothstep(fx.fract());
+            let ty = smoothstep(fy.fract());
+            let top = lerp(at(x0, y0), at(x0 + 1, y0), tx);
+            let bottom = lerp(at(x0, y0 + 1), at(x0 + 1, y0 + 1), tx);
+            field.push(lerp(top, bottom, ty));
+        }
+    }
+    field
+}
+
+/// Averages every tile with its reciprocal so the field obeys the builder's
+/// symmetry.
+fn symmetrise(builder: &WorldBuilder, field: Vec<f32>) -> Vec<f32> {
+    let symmetric = (0..field.len())
+        .map(|i| (field[i] + field[builder.get_reciprocal_by_index(i)]) / 2.0)
+        .collect();
+    normalise(symmetric)
+}
+
+fn normalise(field: Vec<f32>) -> Vec<f32> {
+    let min = field.iter().copied().fold(f32::INFINITY, f32::min);
+    let max = field.iter().copied().fold(f32::NEG_INFINITY, f32::max);
+    let range = max - min;
+    if range <= f32::EPSILON {
+        return vec![0.5; field.len()];
+    }
+    field
+        .into_iter()
+        .map(|value| (value - min) / range)
+        .collect()
+}
+
+fn lerp(a: f32, b: f32, t: f32) -> f32 {
+    a + (b - a) * t
+}
+
+fn smoothstep(t: f32) -> f32 {
+    t * t * (3.0 - 2.0 * t)
+}
+
+#[test]
+fn test_fractal_noise_is_normalised() {
+    let field = fractal_noise(13, 9, 4.0, 3, 0.5);
+    assert_eq!(field.len(), 13 * 9);
+    assert!(field.iter().all(|value| (0.0..=1.0).contains(value)));
+    assert!(field.contains(&0.0));
+    assert!(field.contains(&1.0));
+}
@@ -0,0 +1,251 @@
+//! Pipe walls splitting the map between the headquarters, with breakable
+//! seams.
+
+use rand::seq::SliceRandom;
+use world_core::{Coordinates, TileType, World};
+
+use crate::{GenerationError, WorldBuilder};
+
+/// Lays pipes along the tiles roughly equidistant from two opposing
+/// headquarters, opens `seams` breakthrough points on each side, and locks
+/// the whole wall. Locked tiles already in the wall's path, such as a road
+/// between the headquarters, can't be piped over, so each crossing becomes
+/// a seam instead (properties are left standing) and counts against
+/// `seams`.
+pub fn add_pipe_walls(builder: &mut WorldBuilder, seams: u32) -> Result<(), GenerationError> {
+    let Some((hq_1, hq_2)) = builder.opposing_headquarters() else {
+        return Err(GenerationError::MissingHeadquarters);
+    };
+    let balance = balance(&builder.world, &hq_1, &hq_2);
+
+    let (crossing, wall): (Vec<usize>, Vec<usize>) = (0..builder.world.tiles.len())
+        .filter(|&i| balance[i].abs() <= 1)
+        .partition(|&i| builder.is_locked(i));
+    for &i in &wall {
+        builder.world.update_tile_by_index(i, TileType::Pipe);
+        builder.lock(i);
+    }
+    for &i in &crossing {
+        if !builder.world.tile_at(i).is_property() {
+            builder.world.update_tile_by_index(i, TileType::PipeSeam);
+        }
+    }
+    let mut opened = crossings(builder, &crossing);
+
+    // Where the wall is two tiles thick a seam needs a tile from each side.
+    let mut candidates: Vec<usize> = wall.iter().copied().filter(|&i| balance[i] <= 0).collect();
+    candidates.shuffle(&mut rand::thread_rng());
+    for i in candidates {
+        if opened >= seams {
+            break;
+        }
+        if *builder.world.tile_at(i) != TileType::Pipe {
+            continue;
+        }
+        let mut seam = vec![i];
+        if balance[i] == -1 {
+            let world = &builder.world;
+            let across = world
+                .get_neighbours(&world.coords_from_index(i))
+                .into_iter()
+                .flatten()
+                .map(|coords| world.index_from_coords(&coords))
+                .find(|&n| balance[n] == 1);
+            match across {
+                Some(n) if wall.contains(&n) => seam.push(n),
+                _ => continue,
+            }
+        }
+        for i in seam {
+            let rcp_i = builder.get_reciprocal_by_index(i);
+            builder.world.update_tile_by_index(i, TileType::PipeSeam);
+            if wall.contains(&rcp_i) {
+                builder
+                    .world
+                    .update_tile_by_index(rcp_i, TileType::PipeSeam);
+            }
+        }
+        opened += 1;
+    }
+    if opened < seams {
+        return Err(GenerationError::NotEnoughSpace {
+            tile_type: TileType::PipeSeam,
+            placed: opened,
+            requested: seams,
+        });
+    }
+    Ok(())
+}
+
+/// How many separate openings the locked `tiles` make in the wall. A group
+/// of touching tiles and its reciprocal count as one, like an opened seam.
+fn crossings(builder: &WorldBuilder, tiles: &[usize]) -> u32 {
+    let world = &builder.world;
+    let mut seen = vec![false; world.tiles.len()];
+    let mut count = 0;
+    for &start in tiles {
+        if seen[start] {
+            continue;
+        }
+        count += 1;
+        let mut stack = vec![start, builder.get_reciprocal_by_index(start)];
+        while let Some(i) = stack.pop() {
+            if seen[i] || !tiles.contains(&i) {
+                continue;
+            }
+            seen[i] = true;
+            stack.extend(
+                world
+                    .get_neighbours(&world.coords_from_index(i))
+                    .into_iter()
+                    .flatten()
+                    .map(|coords| world.index_from_coords(&coords)),
+            );
+            stack.push(builder.get_reciprocal_by_index(i));
+        }
+    }
+    count
+}
+
+/// For each tile, the Manhattan distance to `a` minus the distance to `b`.
+/// Neighbouring tiles differ by 0 or 2, so a route from `a`'s side to `b`'s
+/// has to pass through -1, 0 or 1.
+fn balance(world: &World, a: &Coordinates, b: &Coordinates) -> Vec<i64> {
+    let manhattan = |c: &Coordinates, d: &Coordinates| {
+        (c.x as i64 - d.x as i64).abs() + (c.y as i64 - d.y as i64).abs()
+    };
+    (0..world.tiles.len())
+        .map(|i| {
+            let coords = world.coords_from_index(i);
+            manhattan(&coords, a) - manhattan(&coords, b)
+        })
+        .collect()
+}
+
+#[test]
+fn test_pipe_walls_divide_the_map() {
+    use crate::validation::{movement_costs_from, validate};
+    use crate::Config;
+    use world_core::{MovementType, Player};
+
+    let cases = [
+        (9, 7, 0, false),
+        (10, 10, 0, false),
+        (9, 7, 2, false),
+        (10, 10, 1, false),
+        (9, 7, 1, true),
+        (10, 10, 2, true),
+    ];
+    for (width, height, seams, road) in cases {
+        let mut builder = WorldBuilder::new(Config { height, width });
+        let hq = Coordinates::new(1, 2);
+        let rcp = builder.get_reciprocal(&hq);
+        let hq_i = builder.world.index_from_coords(&hq);
+        let rcp_i = builder.world.index_from_coords(&rcp);
+        builder
+            .world
+            .update_tile_by_index(hq_i, TileType::Hq(Player::One));
+        builder
+            .world
+            .update_tile_by_index(rcp_i, TileType::Hq(Player::Two));
+        builder.lock(hq_i);
+        builder.lock(rcp_i);
+        if road {
+            builder = builder.try_join_headquarters(TileType::Road).unwrap();
+        }
+        let builder = builder.try_add_pipe_walls(seams).unwrap();
+        let world = &builder.world;
+
+        for i in 0..world.tiles.len() {
+            let rcp_i = world.index_from_coords(
+                &world_core::Symmetry::Rotational.reciprocal(world, &world.coords_from_index(i)),
+            );
+            assert_eq!(
+                std::mem::discriminant(world.tile_at(i)),
+                std::mem::discriminant(world.tile_at(rcp_i))
+            );
+        }
+        for (i, tile) in world.tiles.iter().enumerate() {
+            if matches!(tile, TileType::Pipe | TileType::PipeSeam) {
+                assert!(builder.is_locked(i));
+            }
+        }
+        let seam_count = world
+            .tiles
+            .iter()
+            .filter(|tile| **tile == TileType::PipeSeam)
+            .count();
+        assert_eq!(seam_count == 0, seams == 0);
+
+        // The seams are the only way through, and breaking them opens a
+        // route between the headquarters.
+        assert!(movement_costs_from(world, &hq, MovementType::Foot)[rcp_i].is_none());
+        let mut broken = world.clone();
+        for tile in broken.tiles.iter_mut() {
+            if *tile == TileType::PipeSeam {
+                *tile = TileType::Plains;
+            }
+        }
+        let costs = movement_costs_from(&broken, &hq, MovementType::Foot);
+        assert_eq!(costs[rcp_i].is_some(), seams > 0);
+        assert_eq!(validate(world, MovementType::Foot).is_valid(), seams > 0);
+    }
+}
+
+#[test]
+fn test_pipe_walls_divide_teams() {
+    use crate::teams::Teams;
+    use crate::validation::movement_costs_from;
+    use crate::Config;
+    use world_core::{MovementType, Player};
+
+    // Teammates come first in tile order, so the wall has to look past them.
+    let mut builder = WorldBuilder::new(Config {
+        height: 10,
+        width: 10,
+    });
+    builder.teams = Teams::two_vs_two([Player::One, Player::Three], [Player::Two, Player::Four]);
+    let hqs = [
+        (Coordinates::new(1, 1), Player::One),
+        (Coordinates::new(3, 1), Player::Three),
+        (Coordinates::new(8, 8), Player::Two),
+        (Coordinates::new(6, 8), Player::Four),
+    ];
+    for (coords, player) in hqs {
+        let i = builder.world.index_from_coords(&coords);
+        builder.world.update_tile_by_index(i, TileType::Hq(player));
+        builder.lock(i);
+    }
+    let world = builder.try_add_pipe_walls(0).unwrap().world;
+
+    let costs = movement_costs_from(&world, &hqs[0].0, MovementType::Foot);
+    let reachable = |coords: &Coordinates| costs[world.index_from_coords(coords)].is_some();
+    assert!(reachable(&hqs[1].0));
+    assert!(!reachable(&hqs[2].0));
+    assert!(!reachable(&hqs[3].0));
+}
+
+#[test]
+fn test_pipe_walls_report_missing_seams() {
+    use crate::Config;
+    use world_core::Player;
+
+    // A 3x3 wall leaves no room for a hundred seams.
+    let mut builder = WorldBuilder::new(Config {
+        height: 3,
+        width: 3,
+    });
+    for (i, player) in [(0, Player::One), (8, Player::Two)] {
+        builder.world.update_tile_by_index(i, TileType::Hq(player));
+        builder.lock(i);
+    }
+    let result = builder.try_add_pipe_walls(100);
+    assert!(matches!(
+        result,
+        Err(GenerationError::NotEnoughSpace {
+            tile_type: TileType::PipeSeam,
+            requested: 100,
+            ..
+        })
+    ));
+}
@@ -0,0 +1,196 @@
+//! Named map styles, each a recipe of builder steps with its own densities
+//! and constraints.
+
+use world_core::TileType;
+
+use crate::cluster::ClusterConfig;
+use crate::headquarters::HqConstraints;
+use crate::noise::NoiseConfig;
+use crate::properties::PropertyBudget;
+use crate::terrain::TerrainBudget;
+use crate::{PathStrategy, WorldBuilder};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Preset {
+    /// Islands of land in open sea, which covers 40% of the map. The road
+    /// between the headquarters is laid before the sea, so it always links
+    /// them over land.
+    IslandHopping,
+    /// Crossing roads through dense cities, with com towers to fight over.
+    UrbanWarfare,
+    /// A road through a mountain range between distant headquarters.
+    MountainPass,
+    /// Land cut up by many narrow channels and shoals.
+    RiverDelta,
+    /// Mostly plains with a little cover.
+    OpenField,
+    /// Headquarters away from the edges behind a pipe wall with two seams,
+    /// one of them where the road crosses it.
+    Fortress,
+}
+
+impl Preset {
+    pub const ALL: [Preset; 6] = [
+        Preset::IslandHopping,
+        Preset::UrbanWarfare,
+        Preset::MountainPass,
+        Preset::RiverDelta,
+        Preset::OpenField,
+        Preset::Fortress,
+    ];
+
+    /// The name used on the command line, e.g. `island-hopping`.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Preset::IslandHopping => "island-hopping",
+            Preset::UrbanWarfare => "urban-warfare",
+            Preset::MountainPass => "mountain-pass",
+            Preset::RiverDelta => "river-delta",
+            Preset::OpenField => "open-field",
+            Preset::Fortress => "fortress",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Preset> {
+        Preset::ALL.into_iter().find(|preset| preset.name() == name)
+    }
+
+    /// Runs every step of the recipe, from headquarters to the final fill.
+    pub fn apply(&self, builder: WorldBuilder) -> WorldBuilder {
+        match self {
+            Preset::IslandHopping => builder
+                .create_headquarters()
+                .join_headquarters(TileType::Road)
+                .add_noise_terrain(NoiseConfig {
+                    scale: 6.0,
+                    sea_level: 0.45,
+                    shoal_level: 0.52,
+                    mountain_level: 0.88,
+                    ..NoiseConfig::default()
+                })
+                .apply_terrain_budget(TerrainBudget {
+                    plains: 0.35,
+                    forest: 0.1,
+                    mountain: 0.05,
+                    sea: 0.4,
+                })
+                .fill(TileType::Plains),
+            Preset::UrbanWarfare => builder
+                .set_path_strategy(PathStrategy::Shortest { noise: 2.0 })
+                .create_headquarters()
+                .join_headquarters(TileType::Road)
+                .join_headquarters(TileType::Road)
+                .allocate_properties(PropertyBudget {
+                    cities: 10,
+                    factories: 2,
+                    neutral_cities: 16,
+                    neutral_factories: 2,
+                })
+                .add_com_towers(3)
+                .add_forests(0.05)
+                .fill(TileType::Plains),
+            Preset::MountainPass => {
+                let (width, height) = (builder.world.width, builder.world.height);
+                builder
+                    .set_hq_constraints(HqConstraints {
+                        min_separation: Some(width.max(height) / 2),
+                        ..HqConstraints::default()
+                    })
+                    .create_headquarters()
+                    .join_headquarters(TileType::Road)
+                    .add_mountains(0.35)
+                    .fill(TileType::Plains)
+                    .cluster_terrain(ClusterConfig {
+                        passes: 3,
+                        types: vec![TileType::Mountain],
+                        ..ClusterConfig::default()
+                    })
+            }
+            Preset::RiverDelta => builder
+                .create_headquarters()
+                .join_headquarters(TileType::Road)
+                .add_noise_terrain(NoiseConfig {
+                    scale: 3.0,
+                    octaves: 2,
+                    sea_level: 0.3,
+                    shoal_level: 0.4,
+                    forest_moisture: 0.55,
+                    ..NoiseConfig::default()
+                })
+                .fill(TileType::Plains),
+            Preset::OpenField => builder
+                .create_headquarters()
+                .join_headquarters(TileType::Road)
+                .add_forests(0.05)
+                .add_mountains(0.02)
+                .fill(TileType::Plains),
+            Preset::Fortress => builder
+                .set_hq_constraints(HqConstraints {
+                    edge_margin: 2,
+                    ..HqConstraints::default()
+                })
+                .create_headquarters()
+                .join_headquarters(TileType::Road)
+                .add_pipe_walls(2)
+                .allocate_properties(PropertyBudget::default())
+                .add_mountains(0.1)
+                .add_forests(0.1)
+                .fill(TileType::Plains),
+        }
+    }
+}
+
+#[test]
+fn test_presets_build() {
+    use crate::validation::movement_costs_from;
+    use crate::Config;
+    use world_core::MovementType;
+
+    for preset in Preset::ALL {
+        assert_eq!(Preset::from_name(preset.name()), Some(preset));
+        let world = WorldBuilder::new(Config {
+            height: 21,
+            width: 21,
+        })
+        .apply_preset(preset)
+        .build();
+        let world = world.unwrap_or_else(|err| panic!("{}: {}", preset.name(), err));
+        assert!(world.tiles.iter().all(|tile| !tile.is_empty()));
+
+        let count = |matches: fn(&TileType) -> bool| {
+            world.tiles.iter().filter(|tile| matches(tile)).count()
+        };
+        let share =
+            |matches: fn(&TileType) -> bool| count(matches) as f32 / world.tiles.len() as f32;
+        match preset {
+            Preset::IslandHopping => {
+                // The budget holds the sea to within a symmetric pair.
+                assert!(share(|tile| *tile == TileType::Sea) >= 0.4 - 1.5 / 441.0)
+            }
+            Preset::UrbanWarfare => {
+                assert_eq!(count(|tile| matches!(tile, TileType::City(_))), 36);
+                assert_eq!(count(|tile| matches!(tile, TileType::ComTower(_))), 6);
+            }
+            Preset::MountainPass => {
+                assert!(share(|tile| *tile == TileType::Mountain) >= 0.3)
+            }
+            Preset::RiverDelta => assert!(count(|tile| *tile == TileType::Shoal) > 0),
+            Preset::OpenField => {
+                assert!(share(|tile| *tile == TileType::Plains) >= 0.5);
+                assert!(share(|tile| *tile == TileType::Mountain) <= 0.1);
+            }
+            Preset::Fortress => {
+                assert!(count(|tile| *tile == TileType::Pipe) > 0);
+                let hqs = world.find_headquarters();
+                for hq in &hqs {
+                    assert!((2..19).contains(&hq.x) && (2..19).contains(&hq.y));
+                }
+                // The road crosses the wall at a seam, so until one is
+                // broken the headquarters can't reach each other.
+                let costs = movement_costs_from(&world, &hqs[0], MovementType::Foot);
+                assert!(costs[world.index_from_coords(&hqs[1])].is_none());
+            }
+        }
+    }
+    assert_eq!(Preset::from_name("swamp"), None);
+}
@@ -0,0 +1,326 @@
+//! Places every property on the map from a budget of counts.
+
+use rand::seq::SliceRandom;
+use world_core::{MovementType, Player, Symmetry, TileType};
+
+use crate::contested::is_free;
+use crate::fairness::analyse;
+use crate::{GenerationError, WorldBuilder};
+
+/// How many properties to place. Owned counts are per player and are placed
+/// in that player's territory, or their team's once theirs is full. Neutral
+/// counts are totals for the whole map.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PropertyBudget {
+    pub cities: u32,
+    pub factories: u32,
+    pub neutral_cities: u32,
+    pub neutral_factories: u32,
+}
+
+impl Default for PropertyBudget {
+    fn default() -> PropertyBudget {
+        PropertyBudget {
+            cities: 8,
+            factories: 2,
+            neutral_cities: 10,
+            neutral_factories: 2,
+        }
+    }
+}
+
+impl PropertyBudget {
+    /// Tiles needed on a map with `players` headquarters.
+    pub fn tiles_needed(&self, players: u32) -> u32 {
+        (self.cities + self.factories) * players + self.neutral_cities + self.neutral_factories
+    }
+}
+
+/// Places exactly the budgeted properties, and any missing headquarters
+/// first. On failure the map is left as it was.
+pub fn allocate_properties(
+    builder: &mut WorldBuilder,
+    budget: &PropertyBudget,
+) -> Result<(), GenerationError> {
+    builder.place_headquarters()?;
+    let (tiles, locked) = (builder.world.tiles.clone(), builder.locked.clone());
+    let result = allocate(builder, budget);
+    if result.is_err() {
+        builder.world.tiles = tiles;
+        builder.locked = locked;
+    }
+    result
+}
+
+fn allocate(builder: &mut WorldBuilder, budget: &PropertyBudget) -> Result<(), GenerationError> {
+    let sides = sides(builder);
+    let players = sides
+        .iter()
+        .map(|(_, counterpart)| if counterpart.is_some() { 2 } else { 1 })
+        .sum();
+    let needed = budget.tiles_needed(players);
+    let available = (0..builder.world.tiles.len())
+        .filter(|&i| is_free(builder, i))
+        .count() as u32;
+    if available < needed {
+        return Err(GenerationError::MapTooSmall { needed, available });
+    }
+    // Each odd neutral count takes one of the tiles that mirror onto
+    // themselves, and maps with even sides may have none at all.
+    if builder.symmetry != Symmetry::None {
+        let mut singles = (0..builder.world.tiles.len())
+            .filter(|&i| builder.get_reciprocal_by_index(i) == i && is_free(builder, i))
+            .count();
+        for (tile_type, count) in [
+            (TileType::Factory(None), budget.neutral_factories),
+            (TileType::City(None), budget.neutral_cities),
+        ] {
+            if count % 2 == 1 {
+                if singles == 0 {
+                    return Err(GenerationError::UnmirrorableBudget { tile_type, count });
+                }
+                singles -= 1;
+            }
+        }
+    }
+
+    // A player boxed in by a teammate may have next to no territory of their
+    // own, so their properties fall back to anywhere their team is nearest.
+    let nearest = analyse(&builder.world, MovementType::Foot).nearest;
+    let teams = builder.teams.clone();
+    let owns = |i: usize, player: Player, by_team: bool| {
+        nearest[i].is_some_and(|owner| {
+            owner == player
+                || (by_team
+                    && teams.team_of(owner).is_some()
+                    && teams.team_of(owner) == teams.team_of(player))
+        })
+    };
+    for (player, counterpart) in sides {
+        for (tile_type, count) in [
+            (TileType::Factory as fn(_) -> _, budget.factories),
+            (TileType::City, budget.cities),
+        ] {
+            for placed in 0..count {
+                let candidates = [false, true]
+                    .into_iter()
+                    .map(|by_team| {
+                        (0..builder.world.tiles.len())
+                            .filter(|&i| {
+                                let rcp_i = builder.get_reciprocal_by_index(i);
+                                owns(i, player, by_team)
+                                    && is_free(builder, i)
+                                    && counterpart.is_none_or(|counterpart| {
+                                        rcp_i != i
+                                            && owns(rcp_i, counterpart, by_team)
+                                            && is_free(builder, rcp_i)
+                                    })
+                            })
+                            .collect::<Vec<usize>>()
+                    })
+                    .find(|candidates| !candidates.is_empty())
+                    .unwrap_or_default();
+                let Some(&i) = candidates.choose(&mut rand::thread_rng()) else {
+                    return Err(GenerationError::NotEnoughSpace {
+                        tile_type: tile_type(Some(player)),
+                        placed,
+                        requested: count,
+                    });
+                };
+                place(builder, i, tile_type(Some(player)));
+                if let Some(counterpart) = counterpart {
+                    let rcp_i = builder.get_reciprocal_by_index(i);
+                    place(builder, rcp_i, tile_type(Some(counterpart)));
+                }
+            }
+        }
+    }
+
+    place_neutral(builder, TileType::Factory(None), budget.neutral_factories)?;
+    place_neutral(builder, TileType::City(None), budget.neutral_cities)
+}
+
+/// Each player, paired with whoever gets the reciprocal of their properties.
+/// Without symmetry every player is placed for separately.
+pub(crate) fn sides(builder: &WorldBuilder) -> Vec<(Player, Option<Player>)> {
+    let owners: Vec<Player> = builder
+        .world
+        .find_headquarters()
+        .iter()
+        .filter_map(|hq| builder.world.tile_at_coords(hq).owner())
+        .collect();
+    if builder.symmetry == Symmetry::None {
+        return owners.into_iter().map(|player| (player, None)).collect();
+    }
+    let pairs: Vec<(Player, Option<Player>)> = builder
+        .player_pairs()
+        .into_iter()
+        .filter(|(player, counterpart)| owners.contains(player) && owners.contains(counterpart))
+        .map(|(player, counterpart)| (player, Some(counterpart)))
+        .collect();
+    if !pairs.is_empty() || !builder.teams.is_empty() {
+        return pairs;
+    }
+    // A template may give the headquarters to other players.
+    match owners[..] {
+        [player, counterpart, ..] => vec![(player, Some(counterpart))],
+        _ => Vec::new(),
+    }
+}
+
+/// Places `count` neutral tiles in reciprocal pairs. An odd count needs one
+/// tile that is its own reciprocal, such as the centre of the map.
+fn place_neutral(
+    builder: &mut WorldBuilder,
+    tile_type: TileType,
+    count: u32,
+) -> Result<(), GenerationError> {
+    let mut placed = 0;
+    let mut rng = rand::thread_rng();
+    while placed < count {
+        let remaining = count - placed;
+        let candidates: Vec<usize> = (0..builder.world.tiles.len())
+            .filter(|&i| {
+                let rcp_i = builder.get_reciprocal_by_index(i);
+                let fits = match builder.symmetry {
+                    Symmetry::None => true,
+                    _ => (rcp_i == i) == (remaining % 2 == 1),
+                };
+                fits && is_free(builder, i) && is_free(builder, rcp_i)
+            })
+            .collect();
+        let Some(&i) = candidates.choose(&mut rng) else {
+            return Err(GenerationError::NotEnoughSpace {
+                tile_type,
+                placed,
+                requested: count,
+            });
+        };
+        let rcp_i = builder.get_reciprocal_by_index(i);
+        place(builder, i, tile_type);
+        place(builder, rcp_i, tile_type);
+        placed += if rcp_i == i { 1 } else { 2 };
+    }
+    Ok(())
+}
+
+fn place(builder: &mut WorldBuilder, i: usize, tile_type: TileType) {
+    builder.world.update_tile_by_index(i, tile_type);
+    builder.lock(i);
+}
+
+#[test]
+fn test_property_budget_is_met_exactly() {
+    use crate::Config;
+
+    let budget = PropertyBudget {
+        cities: 3,
+        factories: 1,
+        neutral_cities: 5,
+        neutral_factories: 2,
+    };
+    let builder = WorldBuilder::new(Config {
+        height: 11,
+        width: 11,
+    })
+    .try_allocate_properties(budget.clone())
+    .unwrap();
+    let count = |tile_type: TileType| {
+        builder
+            .world
+            .tiles
+            .iter()
+            .filter(|tile| **tile == tile_type)
+            .count()
+    };
+    for player in [Player::One, Player::Two] {
+        assert_eq!(count(TileType::Hq(player)), 1);
+        assert_eq!(count(TileType::City(Some(player))), 3);
+        assert_eq!(count(TileType::Factory(Some(player))), 1);
+    }
+    assert_eq!(count(TileType::City(None)), 5);
+    assert_eq!(count(TileType::Factory(None)), 2);
+
+    let even = WorldBuilder::new(Config {
+        height: 12,
+        width: 12,
+    });
+    assert!(matches!(
+        even.try_allocate_properties(budget.clone()),
+        Err(GenerationError::UnmirrorableBudget {
+            tile_type: TileType::City(None),
+            count: 5
+        })
+    ));
+
+    let small = WorldBuilder::new(Config {
+        height: 3,
+        width: 3,
+    })
+    .create_headquarters();
+    assert!(matches!(
+        small.try_allocate_properties(budget),
+        Err(GenerationError::MapTooSmall {
+            needed: 15,
+            available: 7
+        })
+    ));
+}
+
+#[test]
+fn test_team_properties_spill_into_team_territory() {
+    use crate::teams::Teams;
+    use crate::Config;
+    use world_core::Coordinates;
+
+    // Player Three is walled into a corner by the sea, with no free tile of
+    // their own.
+    let mut builder = WorldBuilder::new(Config {
+        height: 10,
+        width: 10,
+    });
+    builder.teams = Teams::two_vs_two([Player::One, Player::Three], [Player::Two, Player::Four]);
+    let tiles = [
+        ((2, 2), TileType::Hq(Player::One)),
+        ((0, 0), TileType::Hq(Player::Three)),
+        ((1, 0), TileType::Sea),
+        ((0, 1), TileType::Sea),
+    ];
+    for ((x, y), tile_type) in tiles {
+        let coords = Coordinates::new(x, y);
+        let rcp_i = builder
+            .world
+            .index_from_coords(&builder.get_reciprocal(&coords));
+        let i = builder.world.index_from_coords(&coords);
+        let rcp_type = match tile_type {
+            TileType::Hq(Player::One) => TileType::Hq(Player::Two),
+            TileType::Hq(_) => TileType::Hq(Player::Four),
+            tile_type => tile_type,
+        };
+        for (i, tile_type) in [(i, tile_type), (rcp_i, rcp_type)] {
+            builder.world.update_tile_by_index(i, tile_type);
+            builder.lock(i);
+        }
+    }
+    let budget = PropertyBudget {
+        cities: 2,
+        factories: 1,
+        neutral_cities: 0,
+        neutral_factories: 0,
+    };
+    let world = builder.try_allocate_properties(budget).unwrap().world;
+
+    let nearest = analyse(&world, MovementType::Foot).nearest;
+    for player in [Player::One, Player::Two, Player::Three, Player::Four] {
+        let team = [Player::One, Player::Three].contains(&player);
+        let owned: Vec<usize> = (0..world.tiles.len())
+            .filter(|&i| world.tile_at(i).owner() == Some(player))
+            .filter(|&i| !matches!(world.tile_at(i), TileType::Hq(_)))
+            .collect();
+        assert_eq!(owned.len(), 3);
+        for i in owned {
+            let owner = nearest[i].unwrap();
+            assert_eq!([Player::One, Player::Three].contains(&owner), team);
+        }
+    }
+}
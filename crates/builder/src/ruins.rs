@@ -0,0 +1,151 @@
+//! Terrain specific to the Days of Ruin tileset.
+
+use rand::Rng;
+use world_core::TileType;
+
+use crate::{GenerationError, WorldBuilder};
+
+/// Settings for `WorldBuilder::add_ruin_terrain`. Densities are the chance
+/// of each eligible tile being converted.
+#[derive(Debug)]
+pub struct RuinConfig {
+    /// Empty tiles that become wasteland.
+    pub wasteland: f32,
+    /// Empty tiles that become ruins.
+    pub ruins: f32,
+    /// Empty tiles that catch fire.
+    pub fire: f32,
+    /// Sea tiles that become rough sea.
+    pub rough_sea: f32,
+    /// Sea tiles covered in mist.
+    pub mist: f32,
+    /// Meteors to drop, each surrounded by plasma.
+    pub meteors: u32,
+}
+
+impl Default for RuinConfig {
+    fn default() -> RuinConfig {
+        RuinConfig {
+            wasteland: 0.1,
+            ruins: 0.05,
+            fire: 0.0,
+            rough_sea: 0.2,
+            mist: 0.1,
+            meteors: 0,
+        }
+    }
+}
+
+pub fn add_ruin_terrain(
+    builder: &mut WorldBuilder,
+    config: &RuinConfig,
+) -> Result<(), GenerationError> {
+    for _ in 0..config.meteors {
+        drop_meteor(builder);
+    }
+    convert(builder, TileType::Sea, TileType::RoughSea, config.rough_sea)?;
+    convert(builder, TileType::Sea, TileType::Mist, config.mist)?;
+    convert(
+        builder,
+        TileType::Empty,
+        TileType::Wasteland,
+        config.wasteland,
+    )?;
+    convert(builder, TileType::Empty, TileType::Ruins, config.ruins)?;
+    convert(builder, TileType::Empty, TileType::Fire, config.fire)
+}
+
+/// Turns unlocked `from` tiles into `to`, together with their reciprocals.
+fn convert(
+    builder: &mut WorldBuilder,
+    from: TileType,
+    to: TileType,
+    density: f32,
+) -> Result<(), GenerationError> {
+    if !(0.0..=1.0).contains(&density) {
+        return Err(GenerationError::InvalidDensity(density));
+    }
+    for i in 0..builder.world.tiles.len() {
+        let rcp_i = builder.get_reciprocal_by_index(i);
+        let convertible = |i| *builder.world.tile_at(i) == from && !builder.is_locked(i);
+        if convertible(i) && convertible(rcp_i) && rand::thread_rng().gen_bool(density as f64) {
+            builder.world.update_tile_by_index(i, to);
+            builder.world.update_tile_by_index(rcp_i, to);
+        }
+    }
+    Ok(())
+}
+
+/// Places a meteor on a random empty tile, with plasma on the empty tiles
+/// around it, and the same at its reciprocal.
+fn drop_meteor(builder: &mut WorldBuilder) {
+    let world = &builder.world;
+    let free = |i: usize| {
+        let rcp_i = builder.get_reciprocal_by_index(i);
+        [i, rcp_i]
+            .iter()
+            .all(|&i| world.tile_at(i).is_empty() && !builder.is_locked(i))
+    };
+    let candidates: Vec<usize> = (0..world.tiles.len()).filter(|&i| free(i)).collect();
+    if candidates.is_empty() {
+        return;
+    }
+    let meteor = candidates[rand::thread_rng().gen_range(0..candidates.len())];
+    let plasma: Vec<usize> = world
+        .get_neighbours(&world.coords_from_index(meteor))
+        .iter()
+        .flatten()
+        .map(|coords| world.index_from_coords(coords))
+        .filter(|&i| free(i))
+        .collect();
+
+    let changes = plasma
+        .into_iter()
+        .map(|i| (i, TileType::Plasma))
+        .chain([(meteor, TileType::Meteor)])
+        .collect::<Vec<_>>();
+    for (i, tile_type) in changes {
+        let rcp_i = builder.get_reciprocal_by_index(i);
+        builder.world.update_tile_by_index(i, tile_type);
+        builder.world.update_tile_by_index(rcp_i, tile_type);
+    }
+}
+
+#[test]
+fn test_ruin_terrain_needs_days_of_ruin() {
+    use crate::Config;
+    use world_core::Tileset;
+
+    let builder = WorldBuilder::new(Config {
+        height: 8,
+        width: 8,
+    });
+    assert!(matches!(
+        builder.try_add_ruin_terrain(RuinConfig::default()),
+        Err(GenerationError::UnsupportedTile { .. })
+    ));
+
+    let config = RuinConfig {
+        wasteland: 0.5,
+        meteors: 1,
+        ..RuinConfig::default()
+    };
+    let world = WorldBuilder::new(Config {
+        height: 8,
+        width: 8,
+    })
+    .set_tileset(Tileset::DaysOfRuin)
+    .try_add_ruin_terrain(config)
+    .unwrap()
+    .world;
+    assert_eq!(
+        world
+            .tiles
+            .iter()
+            .filter(|t| **t == TileType::Meteor)
+            .count(),
+        2
+    );
+    assert!(world.tiles.contains(&TileType::Plasma));
+    assert!(world.tiles.contains(&TileType::Wasteland));
+}
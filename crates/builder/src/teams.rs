@@ -0,0 +1,124 @@
+//! Team assignments for team battles, such as 2v2.
+
+use world_core::{Player, Players};
+
+/// Two sides of players who fight together. Without teams every player
+/// fights alone.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Teams(pub Vec<Vec<Player>>);
+
+impl Teams {
+    pub fn two_vs_two(first: [Player; 2], second: [Player; 2]) -> Teams {
+        Teams(vec![first.to_vec(), second.to_vec()])
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn team_of(&self, player: Player) -> Option<usize> {
+        self.0.iter().position(|team| team.contains(&player))
+    }
+
+    /// Two equal sides, with no player on both or twice on one.
+    pub fn is_valid(&self) -> bool {
+        let players: Vec<Player> = self.0.iter().flatten().copied().collect();
+        let distinct = players
+            .iter()
+            .enumerate()
+            .all(|(i, player)| !players[..i].contains(player));
+        match &self.0[..] {
+            [first, second] => distinct && !first.is_empty() && first.len() == second.len(),
+            _ => false,
+        }
+    }
+
+    pub fn players(&self) -> Players {
+        match self.0.iter().map(Vec::len).sum() {
+            4 => Players::Four,
+            3 => Players::Three,
+            _ => Players::Two,
+        }
+    }
+}
+
+#[test]
+fn test_teams() {
+    let teams = Teams::two_vs_two([Player::One, Player::Three], [Player::Two, Player::Four]);
+    assert!(teams.is_valid());
+    assert_eq!(teams.team_of(Player::Three), Some(0));
+    assert_eq!(teams.team_of(Player::Four), Some(1));
+    assert!(matches!(teams.players(), Players::Four));
+
+    assert!(!Teams::two_vs_two([Player::One, Player::Two], [Player::Two, Player::Four]).is_valid());
+    assert!(!Teams(vec![vec![Player::One], vec![Player::Two, Player::Three]]).is_valid());
+    assert!(!Teams::default().is_valid());
+}
+
+#[test]
+fn test_team_headquarters_mirror_each_other() {
+    use crate::{Config, WorldBuilder};
+    use world_core::{Symmetry, TileType};
+
+    let teams = Teams::two_vs_two([Player::One, Player::Three], [Player::Two, Player::Four]);
+    for _ in 0..10 {
+        let builder = WorldBuilder::new(Config {
+            height: 16,
+            width: 16,
+        })
+        .set_symmetry(Symmetry::None)
+        .set_teams(teams.clone())
+        .try_create_headquarters()
+        .unwrap();
+        assert_eq!(builder.symmetry, Symmetry::Rotational);
+
+        let world = &builder.world;
+        let hq_of = |player: Player| {
+            world
+                .find_headquarters()
+                .into_iter()
+                .find(|hq| *world.tile_at_coords(hq) == TileType::Hq(player))
+                .unwrap()
+        };
+        assert_eq!(
+            builder.get_reciprocal(&hq_of(Player::One)),
+            hq_of(Player::Two)
+        );
+        assert_eq!(
+            builder.get_reciprocal(&hq_of(Player::Three)),
+            hq_of(Player::Four)
+        );
+        let teammates = hq_of(Player::One).distance(&hq_of(Player::Three));
+        assert!(teammates < hq_of(Player::One).distance(&hq_of(Player::Two)));
+        assert!(teammates < hq_of(Player::One).distance(&hq_of(Player::Four)));
+
+        let report =
+            crate::fairness::analyse(world, world_core::MovementType::Foot).by_team(&builder.teams);
+        assert_eq!(report.players.len(), 2);
+        assert_eq!(report.players[0].territory, report.players[1].territory);
+    }
+
+    let result = WorldBuilder::new(Config {
+        height: 16,
+        width: 16,
+    })
+    .set_teams(teams)
+    .set_symmetry(Symmetry::None)
+    .create_headquarters()
+    .build();
+    assert!(matches!(
+        result,
+        Err(crate::GenerationError::TeamsNeedSymmetry)
+    ));
+
+    let invalid = Teams::two_vs_two([Player::One, Player::Two], [Player::Two, Player::Four]);
+    assert!(matches!(
+        WorldBuilder::new(Config {
+            height: 16,
+            width: 16,
+        })
+        .set_teams(invalid)
+        .build(),
+        Err(crate::GenerationError::InvalidTeams(_))
+    ));
+}
@@ -0,0 +1,211 @@
+//! Hand-painted partial maps that generation fills in around.
+
+use world_core::{Player, Symmetry, TileType, Unit, World, WorldError};
+
+use crate::{GenerationError, WorldBuilder};
+
+/// Copies every non-empty tile of `template` into the builder and locks it,
+/// along with any units. A tile whose reciprocal is empty in the template is
+/// mirrored there, with its owner swapped for their opposite number, so
+/// designers only have to paint one side.
+pub fn apply_template(builder: &mut WorldBuilder, template: &World) -> Result<(), GenerationError> {
+    let world = &builder.world;
+    if (template.width, template.height) != (world.width, world.height) {
+        return Err(WorldError::SizeMismatch {
+            before: (world.width, world.height),
+            after: (template.width, template.height),
+        }
+        .into());
+    }
+
+    let mut tiles: Vec<(usize, TileType)> = Vec::new();
+    for (i, tile_type) in template.tiles.iter().enumerate() {
+        if tile_type.is_empty() {
+            continue;
+        }
+        tiles.push((i, *tile_type));
+        if builder.symmetry == Symmetry::None {
+            continue;
+        }
+        let rcp_i = builder.get_reciprocal_by_index(i);
+        let mirrored = mirror(builder, tile_type)?;
+        match template.tile_at(rcp_i) {
+            TileType::Empty => tiles.push((rcp_i, mirrored)),
+            rcp if *rcp == mirrored => {}
+            _ => {
+                let coords = builder.world.coords_from_index(i);
+                return Err(GenerationError::AsymmetricTemplate {
+                    x: coords.x,
+                    y: coords.y,
+                });
+            }
+        }
+    }
+    for (i, tile_type) in tiles {
+        builder.world.update_tile_by_index(i, tile_type);
+        builder.lock(i);
+    }
+
+    for i in 0..template.tiles.len() {
+        let Some(unit) = template.unit_at(i) else {
+            continue;
+        };
+        builder.world.place_unit(i, Some(*unit));
+        let rcp_i = builder.get_reciprocal_by_index(i);
+        if builder.symmetry != Symmetry::None && rcp_i != i && template.unit_at(rcp_i).is_none() {
+            let owner = counterpart(builder, unit.owner);
+            builder
+                .world
+                .place_unit(rcp_i, Some(Unit { owner, ..*unit }));
+        }
+    }
+    Ok(())
+}
+
+/// `tile_type` as it should appear at its reciprocal.
+fn mirror(builder: &WorldBuilder, tile_type: &TileType) -> Result<TileType, WorldError> {
+    match tile_type.owner() {
+        Some(owner) => {
+            TileType::try_from_id(tile_type.try_to_id()?, Some(counterpart(builder, owner)))
+        }
+        None => Ok(*tile_type),
+    }
+}
+
+/// The player facing `player` across the line of symmetry.
+fn counterpart(builder: &WorldBuilder, player: Player) -> Player {
+    if let [first, second] = &builder.teams.0[..] {
+        if let Some(k) = first.iter().position(|p| *p == player) {
+            return second[k];
+        }
+        if let Some(k) = second.iter().position(|p| *p == player) {
+            return first[k];
+        }
+    }
+    match player {
+        Player::One => Player::Two,
+        Player::Two => Player::One,
+        Player::Three => Player::Four,
+        Player::Four => Player::Three,
+    }
+}
+
+#[test]
+fn test_generation_respects_template() {
+    use crate::cluster::ClusterConfig;
+    use crate::Config;
+    use world_core::{map_format, UnitType};
+
+    let template = map_format::read_map(
+        "0,0,0,0,0,0,0,0,0
+         0,8:1@0:1,3,0,0,0,0,0,0
+         0,0,0,0,0,0,0,0,0
+         0,0,0,2,2,2,0,0,0
+         0,0,0,2,4,2,0,0,0
+         0,0,0,2,2,2,0,0,0
+         0,0,0,0,0,0,0,0,0
+         0,0,0,0,0,0,0,0,0
+         0,0,0,0,0,0,0,0,0",
+    )
+    .unwrap();
+    let config = || Config {
+        height: 9,
+        width: 9,
+    };
+
+    for _ in 0..5 {
+        let world = WorldBuilder::new(config())
+            .try_apply_template(&template)
+            .unwrap()
+            .create_headquarters()
+            .join_headquarters(TileType::Road)
+            .add_forests(0.3)
+            .add_mountains(0.1)
+            .fill(TileType::Plains)
+            .cluster_terrain(ClusterConfig::default())
+            .build()
+            .unwrap();
+        for (i, tile_type) in template.tiles.iter().enumerate() {
+            if !tile_type.is_empty() {
+                assert_eq!(world.tile_at(i), tile_type);
+            }
+        }
+        // The painted side is mirrored, headquarters and units included.
+        assert_eq!(world.find_headquarters().len(), 2);
+        assert_eq!(world.tiles[7 * 9 + 7], TileType::Hq(Player::Two));
+        assert_eq!(world.tiles[7 * 9 + 6], TileType::Forest);
+        assert_eq!(
+            world.unit_at(7 * 9 + 7),
+            Some(&Unit::new(UnitType::Infantry, Player::Two))
+        );
+    }
+
+    let mut asymmetric = template.clone();
+    asymmetric.tiles[7 * 9 + 6] = TileType::Mountain;
+    assert!(matches!(
+        WorldBuilder::new(config()).try_apply_template(&asymmetric),
+        Err(GenerationError::AsymmetricTemplate { .. })
+    ));
+    let small = map_format::read_map("0,0\n0,0\n").unwrap();
+    assert!(WorldBuilder::new(config())
+        .try_apply_template(&small)
+        .is_err());
+}
+
+#[test]
+fn test_missing_headquarters_are_placed() {
+    use crate::teams::Teams;
+    use crate::Config;
+    use world_core::Coordinates;
+
+    let mut template = World {
+        height: 12,
+        units: Vec::new(),
+        width: 12,
+        tiles: vec![TileType::Empty; 144],
+    };
+    let painted = Coordinates::new(2, 3);
+    template.update_tile(&painted, TileType::Hq(Player::One));
+    let owners = |world: &World| {
+        let mut owners: Vec<Player> = world
+            .find_headquarters()
+            .iter()
+            .filter_map(|hq| world.tile_at_coords(hq).owner())
+            .collect();
+        owners.sort_by_key(|player| *player as u8);
+        owners
+    };
+
+    for _ in 0..5 {
+        let world = WorldBuilder::new(Config {
+            height: 12,
+            width: 12,
+        })
+        .set_symmetry(Symmetry::None)
+        .try_apply_template(&template)
+        .unwrap()
+        .create_headquarters()
+        .join_headquarters(TileType::Road)
+        .build()
+        .unwrap();
+        assert_eq!(*world.tile_at_coords(&painted), TileType::Hq(Player::One));
+        assert_eq!(owners(&world), [Player::One, Player::Two]);
+
+        let teams = Teams::two_vs_two([Player::One, Player::Three], [Player::Two, Player::Four]);
+        let world = WorldBuilder::new(Config {
+            height: 12,
+            width: 12,
+        })
+        .set_teams(teams)
+        .try_apply_template(&template)
+        .unwrap()
+        .create_headquarters()
+        .build()
+        .unwrap();
+        assert_eq!(*world.tile_at_coords(&painted), TileType::Hq(Player::One));
+        assert_eq!(
+            owners(&world),
+            [Player::One, Player::Two, Player::Three, Player::Four]
+        );
+    }
+}
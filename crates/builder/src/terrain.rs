@@ -0,0 +1,270 @@
+//! Terrain ratio targets for the whole map, independent of step order.
+
+use std::fmt;
+
+use serde::Serialize;
+use world_core::TileType;
+
+use crate::utils::choose;
+use crate::{GenerationError, WorldBuilder};
+
+/// How much more likely a tile is to be picked for each neighbour that
+/// already has the terrain, so budgets grow clusters rather than noise.
+const CLUSTER_WEIGHT: f32 = 4.0;
+
+/// Fractions of the whole map for each terrain. Whatever isn't budgeted is
+/// left empty for later steps.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TerrainBudget {
+    pub plains: f32,
+    pub forest: f32,
+    pub mountain: f32,
+    pub sea: f32,
+}
+
+impl Default for TerrainBudget {
+    fn default() -> TerrainBudget {
+        TerrainBudget {
+            plains: 0.6,
+            forest: 0.2,
+            mountain: 0.1,
+            sea: 0.1,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct TerrainRatio {
+    pub terrain: &'static str,
+    pub target: f32,
+    pub actual: f32,
+}
+
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct TerrainReport {
+    pub ratios: Vec<TerrainRatio>,
+}
+
+impl TerrainBudget {
+    fn targets(&self) -> [(&'static str, TileType, f32); 4] {
+        [
+            ("plains", TileType::Plains, self.plains),
+            ("forest", TileType::Forest, self.forest),
+            ("mountain", TileType::Mountain, self.mountain),
+            ("sea", TileType::Sea, self.sea),
+        ]
+    }
+
+    /// Every fraction is within `0.0..=1.0` and together they cover at most
+    /// the whole map.
+    pub fn is_valid(&self) -> bool {
+        let fractions = self.targets().map(|(_, _, fraction)| fraction);
+        fractions
+            .iter()
+            .all(|fraction| (0.0..=1.0).contains(fraction))
+            && fractions.iter().sum::<f32>() <= 1.0 + f32::EPSILON
+    }
+
+    /// The actual share of each terrain on `world` against its target.
+    pub fn report(&self, world: &world_core::World) -> TerrainReport {
+        let total = world.tiles.len().max(1) as f32;
+        let ratios = self
+            .targets()
+            .into_iter()
+            .map(|(terrain, tile_type, target)| TerrainRatio {
+                terrain,
+                target,
+                actual: count(&world.tiles, tile_type) as f32 / total,
+            })
+            .collect();
+        TerrainReport { ratios }
+    }
+}
+
+impl TerrainReport {
+    /// The largest gap between an actual share and its target.
+    pub fn max_error(&self) -> f32 {
+        self.ratios
+            .iter()
+            .map(|ratio| (ratio.actual - ratio.target).abs())
+            .fold(0.0, f32::max)
+    }
+}
+
+impl fmt::Display for TerrainReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for ratio in &self.ratios {
+            writeln!(
+                f,
+                "{}: {:.1}% (target {:.1}%)",
+                ratio.terrain,
+                ratio.actual * 100.0,
+                ratio.target * 100.0
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Clears unlocked terrain that is over budget, then grows each terrain that
+/// is under budget from empty tiles, until every count is as close to its
+/// target as symmetry allows. When tiles outside the budget, such as roads
+/// and headquarters, leave too little room for every target, plains give up
+/// the difference.
+pub fn apply_budget(
+    builder: &mut WorldBuilder,
+    budget: &TerrainBudget,
+) -> Result<(), GenerationError> {
+    if !budget.is_valid() {
+        return Err(GenerationError::InvalidTerrainBudget(*budget));
+    }
+    let total = builder.world.tiles.len() as f32;
+    let mut targets = budget
+        .targets()
+        .map(|(_, tile_type, fraction)| (tile_type, (fraction * total).round() as usize));
+    let budgeted = |tile: &TileType| tile.is_empty() || targets.iter().any(|(t, _)| t == tile);
+    let room = builder
+        .world
+        .tiles
+        .iter()
+        .filter(|tile| budgeted(tile))
+        .count();
+    let others: usize = targets[1..].iter().map(|(_, target)| target).sum();
+    targets[0].1 = targets[0].1.min(room.saturating_sub(others));
+
+    for (tile_type, target) in targets {
+        let excess = count(&builder.world.tiles, tile_type).saturating_sub(target);
+        convert(builder, tile_type, TileType::Empty, excess);
+    }
+    for (tile_type, target) in targets.into_iter().rev() {
+        let deficit = target.saturating_sub(count(&builder.world.tiles, tile_type));
+        convert(builder, TileType::Empty, tile_type, deficit);
+    }
+    builder.terrain_budget = Some(*budget);
+    Ok(())
+}
+
+/// Turns up to `amount` unlocked `from` tiles into `to`, together with their
+/// reciprocals, favouring tiles next to `to`.
+fn convert(builder: &mut WorldBuilder, from: TileType, to: TileType, amount: usize) {
+    let mut remaining = amount;
+    while remaining > 0 {
+        let world = &builder.world;
+        let units: Vec<(usize, usize)> = (0..world.tiles.len())
+            .map(|i| (i, builder.get_reciprocal_by_index(i)))
+            .filter(|&(i, rcp_i)| {
+                i <= rcp_i
+                    && [i, rcp_i]
+                        .iter()
+                        .all(|&i| *world.tile_at(i) == from && !builder.is_locked(i))
+            })
+            .collect();
+        let size = |&(i, rcp_i): &(usize, usize)| if i == rcp_i { 1 } else { 2 };
+        // Singles make up an odd remainder, so the count lands exactly.
+        let singles: Vec<(usize, usize)> = units
+            .iter()
+            .copied()
+            .filter(|unit| size(unit) == 1)
+            .collect();
+        let pairs: Vec<(usize, usize)> = units
+            .iter()
+            .copied()
+            .filter(|unit| size(unit) == 2)
+            .collect();
+        let options = if (remaining % 2 == 1 || pairs.is_empty()) && !singles.is_empty() {
+            singles
+        } else if remaining >= 2 {
+            pairs
+        } else {
+            return;
+        };
+        if options.is_empty() {
+            return;
+        }
+
+        let weights: Vec<f32> = options
+            .iter()
+            .map(|&(i, _)| {
+                let neighbours = world
+                    .get_neighbours(&world.coords_from_index(i))
+                    .iter()
+                    .flatten()
+                    .filter(|n| *world.tile_at_coords(n) == to)
+                    .count();
+                1.0 + CLUSTER_WEIGHT * neighbours as f32
+            })
+            .collect();
+        let Some(&(i, rcp_i)) = choose(&options, &weights) else {
+            return;
+        };
+        builder.world.update_tile_by_index(i, to);
+        builder.world.update_tile_by_index(rcp_i, to);
+        remaining -= size(&(i, rcp_i));
+    }
+}
+
+fn count(tiles: &[TileType], tile_type: TileType) -> usize {
+    tiles.iter().filter(|tile| **tile == tile_type).count()
+}
+
+#[test]
+fn test_terrain_budget_hits_targets() {
+    use crate::{Config, PathStrategy};
+
+    let budget = TerrainBudget {
+        plains: 0.5,
+        forest: 0.25,
+        mountain: 0.15,
+        sea: 0.1,
+    };
+    for (width, height) in [(10, 10), (11, 11), (9, 12)] {
+        let (result, trace) = WorldBuilder::new(Config { height, width })
+            .create_headquarters()
+            .add_forests(0.6)
+            .apply_terrain_budget(budget)
+            .build_with_trace();
+        let (world, warnings) = result.unwrap();
+        assert!(warnings.is_empty());
+
+        let report = trace.terrain.unwrap();
+        let total = (width * height) as f32;
+        // Headquarters take up tiles that the plains would have had, and
+        // without a tile that is its own reciprocal an odd count is one short.
+        assert!(report.ratios[0].actual >= 0.5 - 3.5 / total);
+        for ratio in &report.ratios[1..] {
+            assert!((ratio.actual - ratio.target).abs() <= 1.5 / total);
+        }
+        assert_eq!(report.to_string().lines().count(), 4);
+        assert_eq!(budget.report(&world).max_error(), report.max_error());
+    }
+
+    // On a filled map the other terrains are carved out of the plains, and
+    // the road's tiles come out of the plains' share rather than theirs.
+    for (width, height) in [(10, 10), (11, 11), (9, 12)] {
+        let (result, trace) = WorldBuilder::new(Config { height, width })
+            .set_path_strategy(PathStrategy::Shortest { noise: 0.0 })
+            .create_headquarters()
+            .join_headquarters(TileType::Road)
+            .fill(TileType::Plains)
+            .apply_terrain_budget(budget)
+            .build_with_trace();
+        let (world, warnings) = result.unwrap();
+        assert!(warnings.is_empty());
+        // Only the odd tile that symmetry can't place is left over.
+        assert!(count(&world.tiles, TileType::Empty) <= 4);
+
+        let report = trace.terrain.unwrap();
+        let total = (width * height) as f32;
+        for ratio in &report.ratios[1..] {
+            assert!((ratio.actual - ratio.target).abs() <= 1.5 / total);
+        }
+        let road = count(&world.tiles, TileType::Road) as f32 / total;
+        assert!(report.ratios[0].actual <= 0.5 - road + 0.5 / total);
+    }
+
+    let invalid = TerrainBudget {
+        plains: 0.9,
+        ..TerrainBudget::default()
+    };
+    assert!(!invalid.is_valid());
+}
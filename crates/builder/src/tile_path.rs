@@ -2,7 +2,10 @@ use std::f32::consts::PI;
 
 use world_core::{Coordinates, Symmetry, TileCollection, TileType};
 
-use crate::{utils, WorldBuilder};
+use crate::{noise, utils, validation, WorldBuilder};
+
+// Scale used to turn perturbed tile costs into integers for Dijkstra.
+const COST_SCALE: f32 = 100.0;
 
 pub struct TilePath<'a> {
     pub tiles: Vec<Coordinates>,
@@ -15,9 +18,20 @@ pub struct TilePath<'a> {
 }
 
 pub struct NextStepErr;
+#[derive(Debug)]
 pub enum PathGenerationError {
     NoNextTileErr(Vec<Coordinates>, Vec<Coordinates>),
     InfiniteLoopErr(Vec<Coordinates>, Vec<Coordinates>),
+    NoRoute,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum PathStrategy {
+    /// Weighted random walk that favours heading towards the destination.
+    RandomWalk,
+    /// Cheapest route over tile costs perturbed by fractal noise. `noise` is
+    /// the largest extra cost added to a tile, relative to a base cost of 1.
+    Shortest { noise: f32 },
 }
 
 impl TilePath<'_> {
@@ -38,11 +52,7 @@ impl TilePath<'_> {
 
         let tile = utils::choose(&neighbours, &weights);
         if tile.is_none() {
-            if self.tiles.iter().any(|tile_1| {
-                self.reciprocal_path
-                    .iter()
-                    .any(|tile_2| tile_1.neighbours(tile_2))
-            }) {
+            if self.paths_overlap() {
                 return Ok(());
             }
 
@@ -55,20 +65,26 @@ impl TilePath<'_> {
         self.tiles.push(*tile);
         self.reciprocal_path.push(self.builder.get_reciprocal(tile));
 
-        if let Some(visualiser) = &self.builder.visualiser {
-            visualiser.visualise_with_extra_tiles(&self.builder.world, &self.to_tile_collection());
-        }
+        let changes = self.to_tile_collection();
+        self.builder
+            .notify(|observer| observer.on_tiles_changed(&self.builder.world, &changes));
         return Ok(());
     }
 
     pub fn is_complete(&mut self) -> bool {
         let last_tile = self.tiles.last().unwrap_or(&self.from);
         let path_meets_destination = last_tile.neighbours(&self.to);
-        let paths_overlap = self
-            .tiles
-            .iter()
-            .any(|t| self.reciprocal_path.iter().any(|t2| t.neighbours(t2)));
-        return path_meets_destination || paths_overlap;
+        return path_meets_destination || self.paths_overlap();
+    }
+
+    /// Without symmetry the reciprocal path is the path itself, so it must
+    /// reach the destination.
+    fn paths_overlap(&self) -> bool {
+        self.symmetry != Symmetry::None
+            && self
+                .tiles
+                .iter()
+                .any(|t| self.reciprocal_path.iter().any(|t2| t.neighbours(t2)))
     }
 
     pub fn delete_last(&mut self, n: u32) {
@@ -76,7 +92,7 @@ impl TilePath<'_> {
             self.tiles.pop();
             self.reciprocal_path.pop();
         }
-        println!("self.tiles.last(): {:?}", self.tiles.last());
+        log::trace!("Backtracked path to {:?}", self.tiles.last());
     }
 
     fn calculate_weight(&self, tile: &Coordinates) -> f32 {
@@ -90,7 +106,8 @@ impl TilePath<'_> {
             return 0.0;
         };
 
-        // Remove if tile has two road neighbours.
+        // Remove if tile has two road neighbours. Without symmetry the
+        // destination doesn't count, as reaching it completes the path.
         let world = &self.builder.world;
         let neighbours = world.get_neighbours(tile);
 
@@ -101,7 +118,7 @@ impl TilePath<'_> {
                 self.tiles.contains(&n)
                     // || self.reciprocal_path.contains(&n)
                     || n == &self.from
-                    || n == &self.to
+                    || (n == &self.to && self.symmetry != Symmetry::None)
             })
             .count()
             > 1
@@ -136,16 +153,13 @@ impl TilePath<'_> {
             reciprocal_path: Vec::new(),
         };
 
-        println!(
-            "Generating path from (path.from, path.to): {:?}",
-            (path.from, path.to)
-        );
+        log::debug!("Generating path from {:?} to {:?}", path.from, path.to);
 
         let mut err_count = 0;
         let count = 0;
         while !path.is_complete() {
             if count > 10_000 {
-                println!("Infinite loop!");
+                log::warn!("Path generation is stuck in a loop");
                 return Err(PathGenerationError::InfiniteLoopErr(
                     path.tiles,
                     path.reciprocal_path,
@@ -156,7 +170,8 @@ impl TilePath<'_> {
                 if err_count > 100 {
                     return Err(err);
                 }
-                println!("err_count: {:?}", err_count);
+                log::debug!("Path is stuck, backtracking {} tiles", err_count);
+                path.builder.record_retry();
                 path.delete_last(err_count);
                 err_count += 1;
             }
@@ -165,6 +180,60 @@ impl TilePath<'_> {
         Ok(path)
     }
 
+    /// Finds the cheapest route over empty tiles, stopping as soon as the path
+    /// reaches the destination or meets its own reciprocal. Only fails if no
+    /// such route exists.
+    pub fn generate_shortest(
+        from: Coordinates,
+        to: Coordinates,
+        symmetry: Symmetry,
+        builder: &WorldBuilder,
+        tile_type: TileType,
+        noise: f32,
+    ) -> Result<TilePath<'_>, PathGenerationError> {
+        let world = &builder.world;
+        let field = noise::fractal_noise(world.width, world.height, 4.0, 2, 0.5);
+        let start = world.index_from_coords(&from);
+        let (costs, previous) = validation::dijkstra(world, &[start], |i| {
+            let tile = world.tile_at(i);
+            let open = tile.is_empty() || *tile == tile_type;
+            (open && i != start).then(|| ((1.0 + noise * field[i]) * COST_SCALE) as u32)
+        });
+
+        let is_goal = |i: usize| {
+            let coords = world.coords_from_index(i);
+            let reciprocal = builder.get_reciprocal(&coords);
+            let meets_reciprocal = coords == reciprocal || coords.neighbours(&reciprocal);
+            coords.neighbours(&to) || (symmetry != Symmetry::None && meets_reciprocal)
+        };
+        let goal = (0..costs.len())
+            .filter(|&i| i != start && costs[i].is_some() && is_goal(i))
+            .min_by_key(|&i| costs[i])
+            .ok_or(PathGenerationError::NoRoute)?;
+
+        let mut tiles = Vec::new();
+        let mut current = Some(goal);
+        while let Some(i) = current.filter(|&i| i != start) {
+            tiles.push(world.coords_from_index(i));
+            current = previous[i];
+        }
+        tiles.reverse();
+        let reciprocal_path = tiles.iter().map(|t| builder.get_reciprocal(t)).collect();
+
+        let path = TilePath {
+            from,
+            to,
+            builder,
+            symmetry,
+            tile_type,
+            tiles,
+            reciprocal_path,
+        };
+        let changes = path.to_tile_collection();
+        builder.notify(|observer| observer.on_tiles_changed(world, &changes));
+        Ok(path)
+    }
+
     pub fn to_tile_collection(&self) -> TileCollection {
         let mut tile_collection = TileCollection::new();
         for tile in &self.tiles {
@@ -236,3 +305,58 @@ fn test_angle_difference_acute() {
     let result_2 = calculate_difference_in_angles(&tile_2, &previous, &end);
     assert_eq!(result_1, result_2);
 }
+
+#[test]
+fn test_shortest_path_meets_reciprocal() {
+    use crate::Config;
+    use world_core::Player;
+
+    let mut builder = WorldBuilder::new(Config {
+        height: 9,
+        width: 9,
+    });
+    let from = Coordinates::new(1, 1);
+    let to = builder.get_reciprocal(&from);
+    builder.world.update_tile(&from, TileType::Hq(Player::One));
+    builder.world.update_tile(&to, TileType::Hq(Player::Two));
+
+    let path = TilePath::generate_shortest(
+        from,
+        to,
+        Symmetry::Rotational,
+        &builder,
+        TileType::Road,
+        0.5,
+    )
+    .unwrap();
+    assert!(path.tiles[0].neighbours(&from));
+    let end = path.tiles.last().unwrap();
+    let rcp_end = builder.get_reciprocal(end);
+    assert!(*end == rcp_end || end.neighbours(&rcp_end) || end.neighbours(&to));
+}
+
+#[test]
+fn test_shortest_path_without_route() {
+    use crate::Config;
+    use world_core::Player;
+
+    let mut builder = WorldBuilder::new(Config {
+        height: 5,
+        width: 5,
+    });
+    builder.world.tiles = vec![TileType::Mountain; 25];
+    let from = Coordinates::new(0, 0);
+    let to = builder.get_reciprocal(&from);
+    builder.world.update_tile(&from, TileType::Hq(Player::One));
+    builder.world.update_tile(&to, TileType::Hq(Player::Two));
+
+    let path = TilePath::generate_shortest(
+        from,
+        to,
+        Symmetry::Rotational,
+        &builder,
+        TileType::Road,
+        0.5,
+    );
+    assert!(matches!(path, Err(PathGenerationError::NoRoute)));
+}
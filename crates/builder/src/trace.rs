@@ -0,0 +1,86 @@
+//! A record of every builder step, for understanding how a map was made.
+
+use std::path::{Path, PathBuf};
+use std::{fs, io};
+
+use serde::Serialize;
+
+use crate::terrain::TerrainReport;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct StepRecord {
+    pub name: &'static str,
+    pub parameters: String,
+    pub tiles_changed: usize,
+    pub retries: u32,
+    pub elapsed_ms: f64,
+    /// The error the step returned, if any.
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct GenerationTrace {
+    pub steps: Vec<StepRecord>,
+    /// Actual terrain ratios against the budget, if one was applied.
+    pub terrain: Option<TerrainReport>,
+}
+
+impl GenerationTrace {
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).expect("Traces only contain serialisable data")
+    }
+
+    /// Writes the trace beside `map_path`, so `maps/river.csv` gets
+    /// `maps/river.trace.json`. Returns the path written to.
+    pub fn save_json(&self, map_path: &Path) -> io::Result<PathBuf> {
+        let path = map_path.with_extension("trace.json");
+        fs::write(&path, self.to_json())?;
+        Ok(path)
+    }
+}
+
+#[test]
+fn test_steps_are_traced() {
+    use crate::{Config, WorldBuilder};
+    use world_core::TileType;
+
+    let (result, trace) = WorldBuilder::new(Config {
+        height: 5,
+        width: 5,
+    })
+    .create_headquarters()
+    .add_forests(2.0)
+    .fill(TileType::Plains)
+    .build_with_trace();
+
+    let (_, warnings) = result.unwrap();
+    assert_eq!(warnings.len(), 1);
+    let names: Vec<_> = trace.steps.iter().map(|step| step.name).collect();
+    assert_eq!(names, ["create_headquarters", "add_forests", "fill"]);
+    assert_eq!(trace.steps[0].tiles_changed, 2);
+    assert!(trace.steps[1].error.is_some());
+    assert_eq!(trace.steps[2].tiles_changed, 23);
+    assert!(trace.to_json().contains("\"name\": \"add_forests\""));
+}
+
+#[test]
+fn test_validation_is_traced_when_enabled() {
+    use crate::validation::ValidationMode;
+    use crate::{Config, WorldBuilder};
+    use world_core::TileType;
+
+    let (result, trace) = WorldBuilder::new(Config {
+        height: 5,
+        width: 5,
+    })
+    .set_validation(ValidationMode::Reject)
+    .create_headquarters()
+    .fill(TileType::Plains)
+    .build_with_trace();
+    assert!(result.is_ok());
+    let last = trace.steps.last().unwrap();
+    assert_eq!(
+        (last.name, last.parameters.as_str()),
+        ("validate", "Reject")
+    );
+}
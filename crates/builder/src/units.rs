@@ -0,0 +1,206 @@
+//! Pre-deployed starting armies.
+
+use rand::seq::SliceRandom;
+use world_core::{Coordinates, Unit, UnitType};
+
+use crate::properties::sides;
+use crate::{GenerationError, WorldBuilder};
+
+/// Settings for `WorldBuilder::deploy_units`.
+#[derive(Clone, Debug)]
+pub struct DeployConfig {
+    /// Funds each player's army may cost in total.
+    pub budget: u32,
+    /// How far from their headquarters units may start.
+    pub radius: u32,
+    /// Unit types to pick from.
+    pub unit_types: Vec<UnitType>,
+}
+
+impl Default for DeployConfig {
+    fn default() -> DeployConfig {
+        DeployConfig {
+            budget: 20000,
+            radius: 4,
+            unit_types: vec![
+                UnitType::Infantry,
+                UnitType::Mech,
+                UnitType::Recon,
+                UnitType::Tank,
+                UnitType::Artillery,
+            ],
+        }
+    }
+}
+
+/// Picks one random army within the budget and deploys a copy of it around
+/// every headquarters. On symmetric maps each player's opposite number gets
+/// the reciprocal of their army.
+pub fn deploy_units(
+    builder: &mut WorldBuilder,
+    config: &DeployConfig,
+) -> Result<(), GenerationError> {
+    if builder.opposing_headquarters().is_none() {
+        return Err(GenerationError::MissingHeadquarters);
+    }
+    let army = choose_army(config);
+    for (player, counterpart) in sides(builder) {
+        let hq = builder
+            .world
+            .find_headquarters()
+            .into_iter()
+            .find(|hq| builder.world.tile_at_coords(hq).owner() == Some(player))
+            .ok_or(GenerationError::MissingHeadquarters)?;
+        for &unit_type in &army {
+            let i = find_spot(
+                builder,
+                &hq,
+                unit_type,
+                config.radius,
+                counterpart.is_some(),
+            )
+            .ok_or(GenerationError::UnitPlacement(unit_type))?;
+            builder
+                .world
+                .place_unit(i, Some(Unit::new(unit_type, player)));
+            if let Some(counterpart) = counterpart {
+                let rcp_i = builder.get_reciprocal_by_index(i);
+                builder
+                    .world
+                    .place_unit(rcp_i, Some(Unit::new(unit_type, counterpart)));
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Random affordable units until nothing else fits in the budget.
+fn choose_army(config: &DeployConfig) -> Vec<UnitType> {
+    let mut rng = rand::thread_rng();
+    let mut funds = config.budget;
+    let mut army = Vec::new();
+    loop {
+        let affordable: Vec<&UnitType> = config
+            .unit_types
+            .iter()
+            .filter(|unit_type| unit_type.cost() <= funds)
+            .collect();
+        let Some(&&unit_type) = affordable.choose(&mut rng) else {
+            return army;
+        };
+        funds -= unit_type.cost();
+        army.push(unit_type);
+    }
+}
+
+/// A random unoccupied tile near `hq` that `unit_type` can stand on. With
+/// `mirrored`, the reciprocal has to be free too, and distinct.
+fn find_spot(
+    builder: &WorldBuilder,
+    hq: &Coordinates,
+    unit_type: UnitType,
+    radius: u32,
+    mirrored: bool,
+) -> Option<usize> {
+    let world = &builder.world;
+    let free = |i: usize| {
+        world.unit_at(i).is_none()
+            && world
+                .tile_at(i)
+                .movement_cost(unit_type.movement_type())
+                .is_some()
+    };
+    let candidates: Vec<usize> = (0..world.tiles.len())
+        .filter(|&i| {
+            let rcp_i = builder.get_reciprocal_by_index(i);
+            world.coords_from_index(i).distance(hq) <= radius as f32
+                && free(i)
+                && (!mirrored || (rcp_i != i && free(rcp_i)))
+        })
+        .collect();
+    candidates.choose(&mut rand::thread_rng()).copied()
+}
+
+#[test]
+fn test_deploy_symmetric_armies() {
+    use crate::Config;
+    use world_core::{Player, TileType};
+
+    let setup = || {
+        let mut builder = WorldBuilder::new(Config {
+            height: 10,
+            width: 10,
+        })
+        .fill(TileType::Plains);
+        let hq = Coordinates::new(2, 2);
+        let rcp = builder.get_reciprocal(&hq);
+        builder.world.update_tile(&hq, TileType::Hq(Player::One));
+        builder.world.update_tile(&rcp, TileType::Hq(Player::Two));
+        builder
+    };
+
+    let config = DeployConfig {
+        budget: 10000,
+        radius: 3,
+        unit_types: vec![UnitType::Infantry],
+    };
+    let builder = setup().try_deploy_units(config).unwrap();
+    let world = &builder.world;
+    let deployed: Vec<usize> = (0..world.tiles.len())
+        .filter(|&i| world.unit_at(i).is_some())
+        .collect();
+    assert_eq!(deployed.len(), 20);
+    for i in deployed {
+        let unit = world.unit_at(i).unwrap();
+        let rcp_unit = world.unit_at(builder.get_reciprocal_by_index(i)).unwrap();
+        assert_eq!(unit.unit_type, UnitType::Infantry);
+        assert_ne!(unit.owner, rcp_unit.owner);
+    }
+
+    let crowded = DeployConfig {
+        budget: 10000,
+        radius: 0,
+        unit_types: vec![UnitType::Infantry],
+    };
+    assert!(matches!(
+        setup().try_deploy_units(crowded),
+        Err(GenerationError::UnitPlacement(UnitType::Infantry))
+    ));
+}
+
+#[test]
+fn test_deploy_team_armies() {
+    use crate::teams::Teams;
+    use crate::Config;
+    use world_core::{Player, TileType};
+
+    let teams = Teams::two_vs_two([Player::One, Player::Three], [Player::Two, Player::Four]);
+    let config = DeployConfig {
+        budget: 3000,
+        radius: 3,
+        unit_types: vec![UnitType::Infantry],
+    };
+    for _ in 0..5 {
+        let builder = WorldBuilder::new(Config {
+            height: 16,
+            width: 16,
+        })
+        .set_teams(teams.clone())
+        .try_create_headquarters()
+        .unwrap()
+        .fill(TileType::Plains)
+        .try_deploy_units(config.clone())
+        .unwrap();
+        let world = &builder.world;
+        for hq in world.find_headquarters() {
+            let player = world.tile_at_coords(&hq).owner().unwrap();
+            let army: Vec<usize> = (0..world.tiles.len())
+                .filter(|&i| world.unit_at(i).is_some_and(|unit| unit.owner == player))
+                .collect();
+            assert_eq!(army.len(), 3, "{:?}", player);
+            for i in army {
+                assert!(world.coords_from_index(i).distance(&hq) <= 3.0);
+            }
+        }
+    }
+}
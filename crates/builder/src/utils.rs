@@ -1,8 +1,20 @@
 use rand::Rng;
 
+use crate::GenerationError;
+
 pub fn choose<'a, T>(items: &'a [T], weights: &[f32]) -> Option<&'a T> {
+    try_choose(items, weights).unwrap_or_else(|err| panic!("{}", err))
+}
+
+pub fn try_choose<'a, T>(
+    items: &'a [T],
+    weights: &[f32],
+) -> Result<Option<&'a T>, GenerationError> {
     if items.len() != weights.len() {
-        panic!("Items and weights are different lengths!")
+        return Err(GenerationError::WeightMismatch {
+            items: items.len(),
+            weights: weights.len(),
+        });
     }
 
     let mut cumulative = 0.0;
@@ -16,7 +28,7 @@ pub fn choose<'a, T>(items: &'a [T], weights: &[f32]) -> Option<&'a T> {
         .collect();
 
     if cumulative == 0.0 {
-        return None;
+        return Ok(None);
     }
 
     let rand_num = rand::thread_rng().gen_range(0.0..cumulative);
@@ -27,5 +39,5 @@ pub fn choose<'a, T>(items: &'a [T], weights: &[f32]) -> Option<&'a T> {
         .map(|(id, _)| *id)
         .unwrap();
 
-    return Some(chosen_item);
+    return Ok(Some(chosen_item));
 }
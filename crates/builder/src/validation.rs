@@ -0,0 +1,264 @@
+//! Reachability checks over the movement-cost model.
+
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, VecDeque};
+
+use world_core::{Coordinates, MovementType, TileType, World};
+
+use crate::WorldBuilder;
+
+// Cost of carving through an impassable tile when repairing, relative to
+// walking over a passable one.
+const CARVE_COST: u32 = 5;
+const MAX_REPAIRS: u32 = 50;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ValidationMode {
+    Off,
+    /// `build` fails if the map is invalid.
+    Reject,
+    /// `build` carves passable routes until the map is valid.
+    Repair,
+}
+
+#[derive(Debug, Default)]
+pub struct ValidationReport {
+    /// Properties that ground units cannot reach from the first headquarters.
+    pub unreachable_properties: Vec<Coordinates>,
+    /// Passable areas cut off from the first headquarters.
+    pub isolated_regions: Vec<Vec<Coordinates>>,
+    /// Pairs of headquarters with no ground route between them.
+    pub separated_headquarters: Vec<(Coordinates, Coordinates)>,
+}
+
+impl ValidationReport {
+    /// Isolated regions without properties are allowed, as islands are common
+    /// on naval maps.
+    pub fn is_valid(&self) -> bool {
+        self.unreachable_properties.is_empty() && self.separated_headquarters.is_empty()
+    }
+}
+
+pub fn validate(world: &World, movement: MovementType) -> ValidationReport {
+    let regions = regions(world, movement);
+    let region_of = |coords: &Coordinates| regions[world.index_from_coords(coords)];
+    let headquarters = world.find_headquarters();
+    let Some(main) = headquarters.first().and_then(region_of) else {
+        return ValidationReport::default();
+    };
+
+    let mut report = ValidationReport::default();
+    for (i, tile) in world.tiles.iter().enumerate() {
+        if tile.is_property() && regions[i] != Some(main) {
+            report
+                .unreachable_properties
+                .push(world.coords_from_index(i));
+        }
+    }
+
+    let region_count = regions.iter().flatten().max().map_or(0, |max| max + 1);
+    for region in (0..region_count).filter(|&region| region != main) {
+        let tiles = (0..regions.len())
+            .filter(|&i| regions[i] == Some(region))
+            .map(|i| world.coords_from_index(i))
+            .collect();
+        report.isolated_regions.push(tiles);
+    }
+
+    for (n, hq) in headquarters.iter().enumerate() {
+        for other in &headquarters[n + 1..] {
+            if region_of(hq) != region_of(other) {
+                report.separated_headquarters.push((*hq, *other));
+            }
+        }
+    }
+    report
+}
+
+/// Labels each passable tile with the index of its connected region. Pipe
+/// seams count as passable, as units can break through them.
+pub fn regions(world: &World, movement: MovementType) -> Vec<Option<usize>> {
+    let mut regions = vec![None; world.tiles.len()];
+    let mut count = 0;
+    for start in 0..world.tiles.len() {
+        if regions[start].is_some() || !traversable(world.tile_at(start), movement) {
+            continue;
+        }
+        regions[start] = Some(count);
+        let mut queue = VecDeque::from([start]);
+        while let Some(i) = queue.pop_front() {
+            for neighbour in world
+                .get_neighbours(&world.coords_from_index(i))
+                .iter()
+                .flatten()
+            {
+                let n = world.index_from_coords(neighbour);
+                if regions[n].is_none() && traversable(world.tile_at(n), movement) {
+                    regions[n] = Some(count);
+                    queue.push_back(n);
+                }
+            }
+        }
+        count += 1;
+    }
+    regions
+}
+
+/// Cheapest movement cost from `start` to every tile, or `None` if a tile
+/// cannot be reached.
+pub fn movement_costs_from(
+    world: &World,
+    start: &Coordinates,
+    movement: MovementType,
+) -> Vec<Option<u32>> {
+    let start = world.index_from_coords(start);
+    let (costs, _) = dijkstra(world, &[start], |i| {
+        world.tile_at(i).movement_cost(movement)
+    });
+    costs
+}
+
+/// Multi-source Dijkstra over the four-way grid. `cost` gives the price of
+/// entering a tile, with `None` for tiles that cannot be entered. Returns the
+/// cost of reaching each tile and the tile it was reached from.
+pub(crate) fn dijkstra(
+    world: &World,
+    sources: &[usize],
+    cost: impl Fn(usize) -> Option<u32>,
+) -> (Vec<Option<u32>>, Vec<Option<usize>>) {
+    let mut costs = vec![None; world.tiles.len()];
+    let mut previous = vec![None; world.tiles.len()];
+    let mut heap = BinaryHeap::new();
+    for &source in sources {
+        costs[source] = Some(0);
+        heap.push(Reverse((0, source)));
+    }
+
+    while let Some(Reverse((distance, i))) = heap.pop() {
+        if costs[i].is_some_and(|best| distance > best) {
+            continue;
+        }
+        for neighbour in world
+            .get_neighbours(&world.coords_from_index(i))
+            .iter()
+            .flatten()
+        {
+            let n = world.index_from_coords(neighbour);
+            let Some(step) = cost(n) else { continue };
+            let next = distance + step;
+            if costs[n].is_none_or(|best| next < best) {
+                costs[n] = Some(next);
+                previous[n] = Some(i);
+                heap.push(Reverse((next, n)));
+            }
+        }
+    }
+    (costs, previous)
+}
+
+/// Turns impassable tiles into plains, along the cheapest route from each
+/// cut-off property to the first headquarters, until the map is valid.
+pub fn repair(builder: &mut WorldBuilder, movement: MovementType) -> ValidationReport {
+    for _ in 0..MAX_REPAIRS {
+        let report = validate(&builder.world, movement);
+        let stranded = report
+            .separated_headquarters
+            .first()
+            .map(|(_, hq)| *hq)
+            .or(report.unreachable_properties.first().copied());
+        let Some(stranded) = stranded else {
+            return report;
+        };
+        if !carve_route(builder, &stranded, movement) {
+            return report;
+        }
+    }
+    validate(&builder.world, movement)
+}
+
+fn carve_route(builder: &mut WorldBuilder, from: &Coordinates, movement: MovementType) -> bool {
+    let world = &builder.world;
+    let regions = regions(world, movement);
+    let Some(main) = world
+        .find_headquarters()
+        .first()
+        .and_then(|hq| regions[world.index_from_coords(hq)])
+    else {
+        return false;
+    };
+
+    let start = world.index_from_coords(from);
+    let (costs, previous) = dijkstra(world, &[start], |i| {
+        if traversable(world.tile_at(i), movement) {
+            Some(1)
+        } else if builder.is_locked(i) {
+            None
+        } else {
+            Some(CARVE_COST)
+        }
+    });
+    let Some(target) = (0..costs.len())
+        .filter(|&i| regions[i] == Some(main) && costs[i].is_some())
+        .min_by_key(|&i| costs[i])
+    else {
+        return false;
+    };
+
+    let mut route = Vec::new();
+    let mut current = Some(target);
+    while let Some(i) = current {
+        if !traversable(world.tile_at(i), movement) {
+            route.push(i);
+        }
+        current = previous[i];
+    }
+    for i in route {
+        let rcp_i = builder.get_reciprocal_by_index(i);
+        builder.world.update_tile_by_index(i, TileType::Plains);
+        builder.world.update_tile_by_index(rcp_i, TileType::Plains);
+    }
+    true
+}
+
+fn traversable(tile_type: &TileType, movement: MovementType) -> bool {
+    tile_type.is_passable(movement) || *tile_type == TileType::PipeSeam
+}
+
+#[cfg(test)]
+fn walled_world() -> WorldBuilder {
+    use crate::Config;
+    use world_core::Player;
+
+    let mut builder = WorldBuilder::new(Config {
+        height: 5,
+        width: 5,
+    });
+    builder.world.tiles = vec![TileType::Plains; 25];
+    for i in 10..15 {
+        builder.world.tiles[i] = TileType::Sea;
+    }
+    builder.world.tiles[1] = TileType::Hq(Player::One);
+    builder.world.tiles[23] = TileType::Hq(Player::Two);
+    builder
+}
+
+#[test]
+fn test_validate_finds_separated_headquarters() {
+    let builder = walled_world();
+    let report = validate(&builder.world, MovementType::Foot);
+    assert!(!report.is_valid());
+    assert_eq!(
+        report.separated_headquarters,
+        vec![(Coordinates::new(1, 0), Coordinates::new(3, 4))]
+    );
+    assert_eq!(report.unreachable_properties, vec![Coordinates::new(3, 4)]);
+    assert_eq!(report.isolated_regions.len(), 1);
+}
+
+#[test]
+fn test_repair_connects_headquarters() {
+    let mut builder = walled_world();
+    let report = repair(&mut builder, MovementType::Foot);
+    assert!(report.is_valid());
+    assert!(validate(&builder.world, MovementType::Foot).is_valid());
+}
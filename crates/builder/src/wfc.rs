@@ -0,0 +1,268 @@
+//! Wave Function Collapse terrain, trained on hand-made maps.
+//!
+//! The model learns which tiles appear next to each other (per direction) and
+//! how often each terrain tile is used, then collapses every `Empty` tile of a
+//! world so that all learned adjacencies hold. Tiles that are already placed
+//! are never changed, but they constrain their neighbours.
+
+use std::collections::{HashMap, HashSet, VecDeque};
+
+use rand::Rng;
+use world_core::{map_format, TileType, TileTypeId, World};
+
+use crate::{utils, GenerationError, WorldBuilder};
+
+const EXAMPLE_MAPS: [&str; 2] = [
+    include_str!("../data/examples/river_crossing.csv"),
+    include_str!("../data/examples/mountain_pass.csv"),
+];
+
+const MAX_ATTEMPTS: u32 = 10;
+
+// Directions follow the order returned by `World::get_neighbours`.
+type Direction = usize;
+
+fn opposite(direction: Direction) -> Direction {
+    direction ^ 1
+}
+
+pub struct WfcModel {
+    adjacency: HashMap<(TileTypeId, Direction), HashSet<TileTypeId>>,
+    weights: HashMap<TileTypeId, f32>,
+}
+
+struct Contradiction;
+
+impl WfcModel {
+    pub fn train(examples: &[World]) -> WfcModel {
+        let mut adjacency: HashMap<(TileTypeId, Direction), HashSet<TileTypeId>> = HashMap::new();
+        let mut weights = HashMap::new();
+
+        for world in examples {
+            for (i, tile) in world.tiles.iter().enumerate() {
+                if tile.is_empty() {
+                    continue;
+                }
+                let id = tile.to_id();
+                if is_candidate(tile) {
+                    *weights.entry(id).or_insert(0.0) += 1.0;
+                }
+
+                let neighbours = world.get_neighbours(&world.coords_from_index(i));
+                for (direction, neighbour) in neighbours.iter().enumerate() {
+                    let Some(neighbour) = neighbour else { continue };
+                    let neighbour = world.tile_at_coords(neighbour);
+                    if neighbour.is_empty() {
+                        continue;
+                    }
+                    adjacency
+                        .entry((id, direction))
+                        .or_default()
+                        .insert(neighbour.to_id());
+                    adjacency
+                        .entry((neighbour.to_id(), opposite(direction)))
+                        .or_default()
+                        .insert(id);
+                }
+            }
+        }
+
+        WfcModel { adjacency, weights }
+    }
+
+    /// Trains a model on the maps bundled in `data/examples`.
+    pub fn from_examples() -> WfcModel {
+        let examples: Vec<World> = EXAMPLE_MAPS
+            .iter()
+            .map(|map| map_format::read_map(map).expect("Bundled example maps are valid"))
+            .collect();
+        WfcModel::train(&examples)
+    }
+
+    /// Returns the builder's tiles with every `Empty` tile collapsed, keeping
+    /// reciprocal tiles identical.
+    pub fn collapse(&self, builder: &WorldBuilder) -> Result<Vec<TileType>, GenerationError> {
+        for attempt in 1..=MAX_ATTEMPTS {
+            if let Ok(tiles) = self.try_collapse(builder, false) {
+                return Ok(tiles);
+            }
+            log::debug!("WFC contradiction on attempt {}", attempt);
+            builder.record_retry();
+        }
+        Err(GenerationError::WfcContradiction)
+    }
+
+    /// Like `collapse`, but resolves contradictions by ignoring the rules that
+    /// caused them instead of failing.
+    pub fn collapse_relaxed(&self, builder: &WorldBuilder) -> Vec<TileType> {
+        match self.try_collapse(builder, true) {
+            Ok(tiles) => tiles,
+            Err(Contradiction) => unreachable!("Relaxed WFC never contradicts itself"),
+        }
+    }
+
+    fn candidates(&self) -> Vec<TileTypeId> {
+        let mut candidates: Vec<_> = self.weights.keys().copied().collect();
+        candidates.sort();
+        candidates
+    }
+
+    fn try_collapse(
+        &self,
+        builder: &WorldBuilder,
+        relax: bool,
+    ) -> Result<Vec<TileType>, Contradiction> {
+        let world = &builder.world;
+        let candidates = self.candidates();
+        let mut domains: Vec<Vec<TileTypeId>> = world
+            .tiles
+            .iter()
+            .map(|tile| match tile {
+                TileType::Empty => candidates.clone(),
+                tile => vec![tile.to_id()],
+            })
+            .collect();
+        let fixed: Vec<bool> = world.tiles.iter().map(|tile| !tile.is_empty()).collect();
+        let mut collapsed = fixed.clone();
+
+        let initial: Vec<usize> = (0..domains.len()).filter(|&i| fixed[i]).collect();
+        self.propagate(world, &mut domains, &fixed, initial, &candidates, relax)?;
+
+        while let Some(i) = lowest_entropy(&domains, &collapsed) {
+            let rcp_i = builder.get_reciprocal_by_index(i);
+            let options: Vec<TileTypeId> = domains[i]
+                .iter()
+                .copied()
+                .filter(|id| domains[rcp_i].contains(id))
+                .collect();
+            let weights: Vec<f32> = options.iter().map(|id| self.weights[id]).collect();
+
+            let chosen = match utils::choose(&options, &weights) {
+                Some(id) => *id,
+                None if relax => self.most_common(&candidates),
+                None => return Err(Contradiction),
+            };
+            for index in [i, rcp_i] {
+                domains[index] = vec![chosen];
+                collapsed[index] = true;
+            }
+            self.propagate(
+                world,
+                &mut domains,
+                &fixed,
+                vec![i, rcp_i],
+                &candidates,
+                relax,
+            )?;
+        }
+
+        let tiles = domains
+            .iter()
+            .zip(world.tiles.iter())
+            .map(|(domain, tile)| match tile {
+                TileType::Empty => TileType::from_id(domain[0], None),
+                tile => *tile,
+            })
+            .collect();
+        Ok(tiles)
+    }
+
+    fn propagate(
+        &self,
+        world: &World,
+        domains: &mut [Vec<TileTypeId>],
+        fixed: &[bool],
+        start: Vec<usize>,
+        candidates: &[TileTypeId],
+        relax: bool,
+    ) -> Result<(), Contradiction> {
+        let mut queue: VecDeque<usize> = start.into();
+        while let Some(i) = queue.pop_front() {
+            let neighbours = world.get_neighbours(&world.coords_from_index(i));
+            for (direction, neighbour) in neighbours.iter().enumerate() {
+                let Some(neighbour) = neighbour else { continue };
+                let n = world.index_from_coords(neighbour);
+                if fixed[n] {
+                    continue;
+                }
+
+                let allowed = self.allowed(&domains[i], direction);
+                let narrowed: Vec<TileTypeId> = domains[n]
+                    .iter()
+                    .copied()
+                    .filter(|id| allowed.as_ref().is_none_or(|a| a.contains(id)))
+                    .collect();
+                if narrowed.len() == domains[n].len() {
+                    continue;
+                }
+                if narrowed.is_empty() {
+                    if !relax {
+                        return Err(Contradiction);
+                    }
+                    domains[n] = candidates.to_vec();
+                    continue;
+                }
+                domains[n] = narrowed;
+                queue.push_back(n);
+            }
+        }
+        Ok(())
+    }
+
+    /// Tiles allowed in `direction` from any tile in `domain`. `None` means the
+    /// model has never seen one of the tiles, so it places no constraint.
+    fn allowed(&self, domain: &[TileTypeId], direction: Direction) -> Option<HashSet<TileTypeId>> {
+        let mut allowed = HashSet::new();
+        for id in domain {
+            allowed.extend(self.adjacency.get(&(*id, direction))?);
+        }
+        Some(allowed)
+    }
+
+    fn most_common(&self, candidates: &[TileTypeId]) -> TileTypeId {
+        *candidates
+            .iter()
+            .max_by(|a, b| self.weights[a].total_cmp(&self.weights[b]))
+            .expect("WFC model has no terrain tiles")
+    }
+}
+
+// Properties and roads are placed by their own steps, so WFC only generates
+// natural terrain.
+fn is_candidate(tile: &TileType) -> bool {
+    !tile.is_property() && *tile != TileType::Road
+}
+
+fn lowest_entropy(domains: &[Vec<TileTypeId>], collapsed: &[bool]) -> Option<usize> {
+    let open: Vec<usize> = (0..domains.len()).filter(|&i| !collapsed[i]).collect();
+    let lowest = open.iter().map(|&i| domains[i].len()).min()?;
+    let lowest: Vec<usize> = open
+        .into_iter()
+        .filter(|&i| domains[i].len() == lowest)
+        .collect();
+    let choice = rand::thread_rng().gen_range(0..lowest.len());
+    Some(lowest[choice])
+}
+
+#[test]
+fn test_wfc_fills_empty_tiles_symmetrically() {
+    use crate::Config;
+    use world_core::Player;
+
+    let model = WfcModel::from_examples();
+    let mut builder = WorldBuilder::new(Config {
+        height: 12,
+        width: 14,
+    });
+    builder.world.tiles[15] = TileType::Hq(Player::One);
+    builder.world.tiles[152] = TileType::Hq(Player::Two);
+
+    let tiles = model.collapse(&builder).unwrap();
+    assert_eq!(tiles[15], TileType::Hq(Player::One));
+    for (i, tile) in tiles.iter().enumerate() {
+        assert!(!tile.is_empty());
+        if !tile.is_property() {
+            assert_eq!(*tile, tiles[builder.get_reciprocal_by_index(i)]);
+        }
+    }
+}
@@ -0,0 +1,291 @@
+//! Interactive step-through debugger for the terminal.
+//!
+//! Generation pauses on every event and waits for a key press:
+//!
+//! | Key          | Action                                            |
+//! |--------------|---------------------------------------------------|
+//! | `n`, space   | Advance to the next event                         |
+//! | `s`          | Run until the end of the current step             |
+//! | `c`          | Run to completion, then pause on the final map    |
+//! | `b` / `f`    | Rewind / fast-forward through recorded events     |
+//! | arrows, hjkl | Move the cursor                                   |
+//! | `p`          | Toggle the path-in-progress overlay               |
+//! | `r`          | Toggle the reciprocal overlay                     |
+//! | `q`          | Stop debugging and let generation finish          |
+
+use std::io::{self, Write};
+
+use colored::Colorize;
+use crossterm::event::{self, Event, KeyCode, KeyEventKind};
+use crossterm::{cursor, execute, terminal};
+use world_core::{Coordinates, GenerationObserver, Symmetry, TileCollection, World};
+
+use crate::terminal_symbol;
+
+struct Snapshot {
+    step: String,
+    world: World,
+    /// Tiles decided on but not yet placed, e.g. a path in progress.
+    overlay: TileCollection,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Command {
+    Next,
+    FinishStep,
+    Continue,
+    Rewind,
+    FastForward,
+    Move(i32, i32),
+    ToggleOverlay,
+    ToggleReciprocal,
+    Quit,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum RunMode {
+    Paused,
+    UntilStepEnd,
+    UntilComplete,
+    Detached,
+}
+
+pub struct Debugger {
+    symmetry: Symmetry,
+    history: Vec<Snapshot>,
+    /// Which snapshot is on screen, so earlier events can be revisited.
+    viewing: usize,
+    cursor: Coordinates,
+    show_overlay: bool,
+    show_reciprocal: bool,
+    run_mode: RunMode,
+}
+
+impl Debugger {
+    pub fn new(symmetry: Symmetry) -> Debugger {
+        Debugger {
+            symmetry,
+            history: Vec::new(),
+            viewing: 0,
+            cursor: Coordinates::new(0, 0),
+            show_overlay: true,
+            show_reciprocal: true,
+            run_mode: RunMode::Paused,
+        }
+    }
+
+    /// Applies `command`, returning `true` if generation should resume.
+    pub fn apply(&mut self, command: Command) -> bool {
+        match command {
+            Command::Next => {
+                self.run_mode = RunMode::Paused;
+                return true;
+            }
+            Command::FinishStep => {
+                self.run_mode = RunMode::UntilStepEnd;
+                return true;
+            }
+            Command::Continue => {
+                self.run_mode = RunMode::UntilComplete;
+                return true;
+            }
+            Command::Quit => {
+                self.run_mode = RunMode::Detached;
+                return true;
+            }
+            Command::Rewind => self.viewing = self.viewing.saturating_sub(1),
+            Command::FastForward => {
+                self.viewing = (self.viewing + 1).min(self.history.len().saturating_sub(1))
+            }
+            Command::Move(dx, dy) => {
+                if let Some(snapshot) = self.history.get(self.viewing) {
+                    let x = (self.cursor.x as i32 + dx).clamp(0, snapshot.world.width as i32 - 1);
+                    let y = (self.cursor.y as i32 + dy).clamp(0, snapshot.world.height as i32 - 1);
+                    self.cursor = Coordinates::new(x as u32, y as u32);
+                }
+            }
+            Command::ToggleOverlay => self.show_overlay = !self.show_overlay,
+            Command::ToggleReciprocal => self.show_reciprocal = !self.show_reciprocal,
+        }
+        false
+    }
+
+    fn record(&mut self, world: &World, overlay: TileCollection) {
+        let step = self
+            .history
+            .last()
+            .map(|snapshot| snapshot.step.clone())
+            .unwrap_or_default();
+        self.history.push(Snapshot {
+            step,
+            world: world.clone(),
+            overlay,
+        });
+        self.viewing = self.history.len() - 1;
+    }
+
+    fn pause(&mut self) {
+        let result = terminal::enable_raw_mode().and_then(|_| {
+            execute!(io::stdout(), terminal::EnterAlternateScreen, cursor::Hide)?;
+            loop {
+                self.draw()?;
+                let command = match event::read()? {
+                    Event::Key(key) if key.kind == KeyEventKind::Press => command_for(key.code),
+                    _ => None,
+                };
+                if command.is_some_and(|command| self.apply(command)) {
+                    break;
+                }
+            }
+            execute!(io::stdout(), cursor::Show, terminal::LeaveAlternateScreen)
+        });
+        let _ = terminal::disable_raw_mode();
+        if let Err(err) = result {
+            log::warn!("Debugger terminal error: {}", err);
+            self.run_mode = RunMode::Detached;
+        }
+    }
+
+    fn draw(&self) -> io::Result<()> {
+        let Some(snapshot) = self.history.get(self.viewing) else {
+            return Ok(());
+        };
+        let mut world = snapshot.world.clone();
+        if self.show_overlay {
+            world.apply_tile_collection(&snapshot.overlay);
+        }
+        let reciprocal = self.symmetry.reciprocal(&world, &self.cursor);
+
+        let mut out = io::stdout();
+        execute!(
+            out,
+            cursor::MoveTo(0, 0),
+            terminal::Clear(terminal::ClearType::All)
+        )?;
+        write!(
+            out,
+            "Step: {}  (event {}/{})\r\n",
+            snapshot.step,
+            self.viewing + 1,
+            self.history.len()
+        )?;
+        for (i, tile_type) in world.tiles.iter().enumerate() {
+            let coords = world.coords_from_index(i);
+            let symbol = if coords == self.cursor {
+                "[]".black().on_white()
+            } else if self.show_reciprocal && coords == reciprocal {
+                "<>".black().on_bright_magenta()
+            } else {
+                terminal_symbol(tile_type)
+            };
+            write!(out, "{}", symbol)?;
+            if coords.x + 1 == world.width {
+                write!(out, "\r\n")?;
+            }
+        }
+
+        let tile = world.tile_at_coords(&self.cursor);
+        let rcp_tile = world.tile_at_coords(&reciprocal);
+        write!(
+            out,
+            "\r\nCursor ({}, {}): {:?}\r\nReciprocal ({}, {}): {:?}\r\n",
+            self.cursor.x, self.cursor.y, tile, reciprocal.x, reciprocal.y, rcp_tile
+        )?;
+        write!(
+            out,
+            "\r\n[n] next  [s] finish step  [c] continue  [b/f] rewind/forward  \
+             [p] path overlay: {}  [r] reciprocal: {}  [q] quit\r\n",
+            on_off(self.show_overlay),
+            on_off(self.show_reciprocal)
+        )?;
+        out.flush()
+    }
+}
+
+impl GenerationObserver for Debugger {
+    fn on_step_start(&mut self, step: &str, world: &World) {
+        self.record(world, TileCollection::new());
+        if let Some(snapshot) = self.history.last_mut() {
+            snapshot.step = step.to_string();
+        }
+        if self.run_mode == RunMode::Paused {
+            self.pause();
+        }
+    }
+    fn on_step_end(&mut self, _step: &str, world: &World) {
+        self.record(world, TileCollection::new());
+        if self.run_mode == RunMode::UntilStepEnd {
+            self.run_mode = RunMode::Paused;
+        }
+        if self.run_mode == RunMode::Paused {
+            self.pause();
+        }
+    }
+    fn on_tiles_changed(&mut self, world: &World, changes: &TileCollection) {
+        self.record(world, changes.clone());
+        if self.run_mode == RunMode::Paused {
+            self.pause();
+        }
+    }
+    fn on_complete(&mut self, world: &World) {
+        self.record(world, TileCollection::new());
+        if self.run_mode != RunMode::Detached {
+            self.pause();
+        }
+    }
+}
+
+fn command_for(key: KeyCode) -> Option<Command> {
+    let command = match key {
+        KeyCode::Char('n') | KeyCode::Char(' ') => Command::Next,
+        KeyCode::Char('s') => Command::FinishStep,
+        KeyCode::Char('c') => Command::Continue,
+        KeyCode::Char('b') => Command::Rewind,
+        KeyCode::Char('f') => Command::FastForward,
+        KeyCode::Char('p') => Command::ToggleOverlay,
+        KeyCode::Char('r') => Command::ToggleReciprocal,
+        KeyCode::Char('q') | KeyCode::Esc => Command::Quit,
+        KeyCode::Up | KeyCode::Char('k') => Command::Move(0, -1),
+        KeyCode::Down | KeyCode::Char('j') => Command::Move(0, 1),
+        KeyCode::Left | KeyCode::Char('h') => Command::Move(-1, 0),
+        KeyCode::Right | KeyCode::Char('l') => Command::Move(1, 0),
+        _ => return None,
+    };
+    Some(command)
+}
+
+fn on_off(value: bool) -> &'static str {
+    if value {
+        "on"
+    } else {
+        "off"
+    }
+}
+
+#[test]
+fn test_debugger_navigation() {
+    use world_core::TileType;
+
+    let world = World {
+        tiles: vec![TileType::Plains; 6],
+        width: 3,
+        height: 2,
+        units: Vec::new(),
+    };
+    let mut debugger = Debugger::new(Symmetry::Rotational);
+    debugger.record(&world, TileCollection::new());
+    debugger.record(&world, vec![(0, TileType::Road)]);
+
+    assert!(!debugger.apply(Command::Move(5, 5)));
+    assert_eq!(debugger.cursor, Coordinates::new(2, 1));
+    assert!(!debugger.apply(Command::Rewind));
+    assert!(!debugger.apply(Command::Rewind));
+    assert_eq!(debugger.viewing, 0);
+    assert!(!debugger.apply(Command::FastForward));
+    assert!(!debugger.apply(Command::FastForward));
+    assert_eq!(debugger.viewing, 1);
+    assert!(!debugger.apply(Command::ToggleOverlay));
+    assert!(!debugger.show_overlay);
+    assert!(debugger.apply(Command::FinishStep));
+    assert_eq!(debugger.run_mode, RunMode::UntilStepEnd);
+}
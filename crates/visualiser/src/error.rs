@@ -0,0 +1,9 @@
+#[derive(Debug, thiserror::Error)]
+pub enum VisualiserError {
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+    #[error(transparent)]
+    Png(#[from] png::EncodingError),
+    #[error("heatmap has {values} values but the world has {tiles} tiles")]
+    SizeMismatch { values: usize, tiles: usize },
+}
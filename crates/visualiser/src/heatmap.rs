@@ -0,0 +1,134 @@
+use colored::{ColoredString, Colorize};
+use world_core::World;
+
+use crate::VisualiserError;
+
+pub type Rgb = (u8, u8, u8);
+
+/// Gradient stops from the lowest to the highest value.
+const GRADIENT: [Rgb; 5] = [
+    (40, 40, 160),
+    (0, 160, 200),
+    (60, 190, 60),
+    (240, 210, 0),
+    (220, 40, 30),
+];
+const NO_DATA: Rgb = (60, 60, 60);
+pub(crate) const LEGEND_STEPS: usize = 20;
+
+/// A scalar value per tile, drawn over a map. Non-finite values, such as the
+/// distance to an unreachable tile, are drawn as "no data".
+#[derive(Clone, Debug)]
+pub struct Heatmap {
+    pub name: String,
+    pub values: Vec<f32>,
+}
+
+impl Heatmap {
+    pub fn new(name: &str, values: Vec<f32>) -> Heatmap {
+        Heatmap {
+            name: name.to_string(),
+            values,
+        }
+    }
+
+    /// Builds a heatmap from movement costs, with unreachable tiles as no data.
+    pub fn from_costs(name: &str, costs: &[Option<u32>]) -> Heatmap {
+        let values = costs
+            .iter()
+            .map(|cost| cost.map_or(f32::NAN, |cost| cost as f32))
+            .collect();
+        Heatmap::new(name, values)
+    }
+
+    pub(crate) fn check_size(&self, world: &World) -> Result<(), VisualiserError> {
+        if self.values.len() != world.tiles.len() {
+            return Err(VisualiserError::SizeMismatch {
+                values: self.values.len(),
+                tiles: world.tiles.len(),
+            });
+        }
+        Ok(())
+    }
+
+    /// The smallest and largest finite values, if there are any.
+    pub fn range(&self) -> Option<(f32, f32)> {
+        self.values
+            .iter()
+            .filter(|value| value.is_finite())
+            .fold(None, |range, &value| match range {
+                None => Some((value, value)),
+                Some((min, max)) => Some((min.min(value), max.max(value))),
+            })
+    }
+
+    /// The colour of tile `i`, given the heatmap's `range`. Take the range
+    /// once per render, as finding it scans every value.
+    pub fn colour_at(&self, i: usize, range: Option<(f32, f32)>) -> Rgb {
+        let value = self.values[i];
+        match range {
+            Some((min, max)) if value.is_finite() => {
+                let t = if max > min {
+                    (value - min) / (max - min)
+                } else {
+                    0.0
+                };
+                gradient(t)
+            }
+            _ => NO_DATA,
+        }
+    }
+
+    /// A colour bar labelled with the range of values, for the terminal.
+    pub fn legend(&self) -> String {
+        let bar: String = (0..LEGEND_STEPS)
+            .map(|step| {
+                let (r, g, b) = gradient(step as f32 / (LEGEND_STEPS - 1) as f32);
+                " ".on_truecolor(r, g, b).to_string()
+            })
+            .collect();
+        let (r, g, b) = NO_DATA;
+        match self.range() {
+            Some((min, max)) => format!(
+                "{}: {} {} {}  {} no data",
+                self.name,
+                min,
+                bar,
+                max,
+                " ".on_truecolor(r, g, b)
+            ),
+            None => format!("{}: no data", self.name),
+        }
+    }
+
+    pub(crate) fn terminal_cell(
+        &self,
+        i: usize,
+        symbol: &str,
+        range: Option<(f32, f32)>,
+    ) -> ColoredString {
+        let (r, g, b) = self.colour_at(i, range);
+        symbol.black().on_truecolor(r, g, b)
+    }
+}
+
+/// Linearly interpolates between the gradient stops, with `t` in `0.0..=1.0`.
+pub fn gradient(t: f32) -> Rgb {
+    let scaled = t.clamp(0.0, 1.0) * (GRADIENT.len() - 1) as f32;
+    let lower = (scaled.floor() as usize).min(GRADIENT.len() - 2);
+    let t = scaled - lower as f32;
+    let (a, b) = (GRADIENT[lower], GRADIENT[lower + 1]);
+    let lerp = |a: u8, b: u8| (a as f32 + (b as f32 - a as f32) * t).round() as u8;
+    (lerp(a.0, b.0), lerp(a.1, b.1), lerp(a.2, b.2))
+}
+
+#[test]
+fn test_heatmap_colours() {
+    let heatmap = Heatmap::from_costs("cost", &[Some(0), Some(5), Some(10), None]);
+    let range = heatmap.range();
+    assert_eq!(range, Some((0.0, 10.0)));
+    assert_eq!(heatmap.colour_at(0, range), GRADIENT[0]);
+    assert_eq!(heatmap.colour_at(1, range), GRADIENT[2]);
+    assert_eq!(heatmap.colour_at(2, range), GRADIENT[4]);
+    assert_eq!(heatmap.colour_at(3, range), NO_DATA);
+}
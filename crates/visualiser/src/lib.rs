@@ -1,42 +1,126 @@
-use core::time;
+use std::cell::Cell;
+use std::path::Path;
 use std::thread::sleep;
+use std::time::{Duration, Instant};
 
 use colored::{ColoredString, Colorize};
-use world_core::{parser::Parser, Player, TileCollection, TileType, World};
+use world_core::{
+    parser::Parser, Coordinates, GenerationObserver, Player, TileCollection, TileDiff, TileType,
+    Unit, UnitType, World,
+};
+
+mod debugger;
+mod error;
+mod heatmap;
+mod png;
+mod recorder;
+pub use debugger::{Command, Debugger};
+pub use error::VisualiserError;
+pub use heatmap::Heatmap;
+use heatmap::Rgb;
+pub use recorder::{Frame, Recorder};
+
+const DEFAULT_FRAME_RATE: u32 = 100;
 
 pub enum VisualiserType {
     Terminal,
-    PNG,
+    /// Renders nothing, for batch generation.
+    Headless,
+}
+
+/// Which generation events produce a frame.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RenderMode {
+    /// Every change, including each tile of a path in progress.
+    EveryChange,
+    /// Only the world at the end of each builder step.
+    StepBoundaries,
 }
 
 pub struct Visualiser {
     parser: Parser,
     visualiser_type: VisualiserType,
+    render_mode: RenderMode,
+    frame_interval: Option<Duration>,
+    last_frame: Cell<Option<Instant>>,
 }
 
 impl Visualiser {
     pub fn new() -> Visualiser {
+        Visualiser::with_type(VisualiserType::Terminal)
+    }
+
+    pub fn headless() -> Visualiser {
+        Visualiser::with_type(VisualiserType::Headless)
+    }
+
+    fn with_type(visualiser_type: VisualiserType) -> Visualiser {
         let parser = Parser::new();
         Visualiser {
             parser,
-            visualiser_type: VisualiserType::Terminal,
+            visualiser_type,
+            render_mode: RenderMode::EveryChange,
+            frame_interval: None,
+            last_frame: Cell::new(None),
         }
+        .with_frame_rate(DEFAULT_FRAME_RATE)
     }
 
+    /// Limits how often frames are drawn. Frames are only delayed by however
+    /// much of the interval is left since the previous frame.
+    pub fn with_frame_rate(mut self, frames_per_second: u32) -> Visualiser {
+        self.frame_interval = Some(Duration::from_secs(1) / frames_per_second.max(1));
+        self
+    }
+
+    pub fn without_frame_limit(mut self) -> Visualiser {
+        self.frame_interval = None;
+        self
+    }
+
+    pub fn with_render_mode(mut self, render_mode: RenderMode) -> Visualiser {
+        self.render_mode = render_mode;
+        self
+    }
+
+    /// Draws an intermediate state within a step, such as a path in progress.
     pub fn visualise_with_extra_tiles(&self, world: &World, tile_collection: &TileCollection) {
-        match self.visualiser_type {
-            VisualiserType::Terminal => self.print_to_terminal(world, Some(tile_collection)),
-            _ => todo!(),
+        if self.render_mode == RenderMode::StepBoundaries {
+            return;
         }
+        self.render(world, Some(tile_collection));
+    }
+
+    /// Draws the world once a builder step has finished.
+    pub fn visualise_step(&self, world: &World) {
+        self.render(world, None);
     }
 
     pub fn visualise(&self, world: &World) {
+        self.render(world, None);
+    }
+
+    fn render(&self, world: &World, tile_collection: Option<&TileCollection>) {
         match self.visualiser_type {
-            VisualiserType::Terminal => self.print_to_terminal(world, None),
-            _ => todo!(),
+            VisualiserType::Terminal => {
+                self.wait_for_next_frame();
+                self.print_to_terminal(world, tile_collection)
+            }
+            VisualiserType::Headless => {}
         }
     }
 
+    fn wait_for_next_frame(&self) {
+        let now = Instant::now();
+        if let (Some(interval), Some(last_frame)) = (self.frame_interval, self.last_frame.get()) {
+            let elapsed = now.duration_since(last_frame);
+            if elapsed < interval {
+                sleep(interval - elapsed);
+            }
+        }
+        self.last_frame.set(Some(Instant::now()));
+    }
+
     pub fn print_to_terminal(&self, world: &World, tile_collection: Option<&TileCollection>) {
         print!("{esc}[2J{esc}[1;1H", esc = 27 as char);
         let new_world = tile_collection.map(|tc| {
@@ -51,43 +135,224 @@ impl Visualiser {
         let top_header = format!("{}{}{}", '╔', bar, '╗');
         let bottom_header = format!("{}{}{}", '╚', bar, '╝');
         println!("{}", top_header);
-        for chunk in world.tiles.chunks(world.width as usize) {
+        for (row, chunk) in world.tiles.chunks(world.width as usize).enumerate() {
             print!("║ ");
-            for tile_type in chunk {
-                let str = terminal_symbol(tile_type);
+            for (column, tile_type) in chunk.iter().enumerate() {
+                let i = row * world.width as usize + column;
+                let str = match world.unit_at(i) {
+                    Some(unit) => unit_symbol(unit),
+                    None => terminal_symbol(tile_type),
+                };
                 print!("{str}");
             }
             print!("║\n");
         }
         println!("{}", bottom_header);
-        let ten_millis = time::Duration::from_millis(10);
-        sleep(ten_millis)
+    }
+
+    /// Draws `heatmap` over the world, keeping property symbols visible,
+    /// followed by a legend.
+    pub fn print_heatmap(&self, world: &World, heatmap: &Heatmap) -> Result<(), VisualiserError> {
+        heatmap.check_size(world)?;
+        let range = heatmap.range();
+        let bar = String::from('═').repeat(world.width as usize * 2 + 1);
+        println!("╔{bar}╗");
+        for (row, chunk) in world.tiles.chunks(world.width as usize).enumerate() {
+            print!("║ ");
+            for (column, tile_type) in chunk.iter().enumerate() {
+                let symbol = if tile_type.is_property() {
+                    "★ "
+                } else {
+                    "  "
+                };
+                let i = row * world.width as usize + column;
+                print!("{}", heatmap.terminal_cell(i, symbol, range));
+            }
+            println!("║");
+        }
+        println!("╚{bar}╝");
+        println!("{}", heatmap.legend());
+        Ok(())
+    }
+
+    /// Draws `before` and `after` side by side. Unchanged tiles are dimmed
+    /// so the differences stand out, and each change is listed below.
+    pub fn print_diff(&self, before: &World, after: &World, diffs: &[TileDiff]) {
+        let bar = String::from('═').repeat(before.width as usize * 2 + 1);
+        println!("╔{bar}╗ ╔{bar}╗");
+        for y in 0..before.height {
+            for world in [before, after] {
+                print!("║ ");
+                for x in 0..world.width {
+                    let coords = Coordinates::new(x, y);
+                    let symbol = terminal_symbol(world.tile_at_coords(&coords));
+                    if diffs.iter().any(|diff| diff.coords == coords) {
+                        print!("{}", symbol.on_bright_magenta());
+                    } else {
+                        print!("{}", symbol.dimmed());
+                    }
+                }
+                print!("║ ");
+            }
+            println!();
+        }
+        println!("╚{bar}╝ ╚{bar}╝");
+        for diff in diffs {
+            println!(
+                "({}, {}): {:?} -> {:?}",
+                diff.coords.x, diff.coords.y, diff.before, diff.after
+            );
+        }
+        println!("{} tiles changed", diffs.len());
+    }
+
+    pub fn save_diff_png(
+        &self,
+        path: &Path,
+        before: &World,
+        after: &World,
+        diffs: &[TileDiff],
+    ) -> Result<(), VisualiserError> {
+        png::save_diff_png(path, before, after, diffs)
+    }
+
+    pub fn save_png(&self, path: &Path, world: &World) -> Result<(), VisualiserError> {
+        png::save_png(path, world, None)
+    }
+
+    /// Saves the world with `heatmap` blended over it and a colour bar along
+    /// the bottom. The range is stored in the image's `Legend` text chunk.
+    pub fn save_heatmap_png(
+        &self,
+        path: &Path,
+        world: &World,
+        heatmap: &Heatmap,
+    ) -> Result<(), VisualiserError> {
+        png::save_png(path, world, Some(heatmap))
+    }
+}
+
+impl GenerationObserver for Visualiser {
+    fn on_step_end(&mut self, _step: &str, world: &World) {
+        self.visualise_step(world);
+    }
+    fn on_tiles_changed(&mut self, world: &World, changes: &TileCollection) {
+        self.visualise_with_extra_tiles(world, changes);
+    }
+    fn on_complete(&mut self, world: &World) {
+        self.visualise(world);
+    }
+}
+
+pub(crate) fn player_rgb(player: Player) -> Rgb {
+    match player {
+        Player::One => (255, 200, 0),
+        Player::Two => (0, 200, 255),
+        Player::Three => (60, 200, 60),
+        Player::Four => (230, 70, 70),
     }
 }
 
 fn colour_player_string(string: &str, player: &Option<Player>) -> ColoredString {
     if let Some(player) = player {
-        return match player {
-            Player::One => string.truecolor(255, 200, 0),
-            Player::Two => string.truecolor(0, 200, 255),
-            _ => todo!(),
-        };
+        let (r, g, b) = player_rgb(*player);
+        return string.truecolor(r, g, b);
     } else {
         return string.truecolor(200, 200, 200);
     }
 }
 
+/// A two letter abbreviation on the owner's colour.
+fn unit_symbol(unit: &Unit) -> ColoredString {
+    let abbreviation = match unit.unit_type {
+        UnitType::Infantry => "In",
+        UnitType::Mech => "Me",
+        UnitType::Recon => "Re",
+        UnitType::Apc => "Ap",
+        UnitType::Tank => "Tk",
+        UnitType::MediumTank => "Md",
+        UnitType::Artillery => "Ar",
+        UnitType::Rockets => "Rk",
+        UnitType::AntiAir => "AA",
+        UnitType::Missiles => "Ms",
+    };
+    let (r, g, b) = player_rgb(unit.owner);
+    abbreviation.black().on_truecolor(r, g, b)
+}
+
 fn terminal_symbol(tile_type: &TileType) -> ColoredString {
     match tile_type {
         TileType::Plains => "▓▓".green().bold(),
         TileType::Mountain => "▓▓".yellow(),
         TileType::Sea => "▓▓".blue(),
+        TileType::Shoal => "▓▓".truecolor(230, 210, 150),
         TileType::City(player) => colour_player_string("▓▓", player),
         TileType::Road => "▓▓".truecolor(3, 3, 3),
         TileType::Forest => "▓▓".truecolor(0, 90, 0),
         TileType::Factory(player) => colour_player_string("★ ", player),
         TileType::Hq(player) => colour_player_string("★ ", &Some(*player)),
+        TileType::Wasteland => "▓▓".truecolor(150, 120, 70),
+        TileType::Ruins => "▒▒".truecolor(140, 140, 140),
+        TileType::RoughSea => "▓▓".truecolor(20, 30, 120),
+        TileType::Mist => "░░".truecolor(190, 200, 220),
+        TileType::Fire => "▲▲".red().bold(),
+        TileType::Meteor => "◆ ".truecolor(140, 60, 200),
+        TileType::Plasma => "▓▓".truecolor(230, 60, 230),
+        TileType::ComTower(player) => colour_player_string("╥ ", player),
+        TileType::TempAirport(player) => colour_player_string("△ ", player),
+        TileType::TempPort(player) => colour_player_string("▽ ", player),
+        TileType::Lab(player) => colour_player_string("⚗ ", player),
+        TileType::Pipe => "██".truecolor(70, 70, 80),
+        TileType::PipeSeam => "▚▚".truecolor(110, 110, 120),
+        TileType::MissileSilo => "⇡ ".truecolor(200, 200, 200),
+        TileType::BlackCannon => "◙ ".truecolor(30, 30, 30),
+        TileType::Laser => "◎ ".truecolor(30, 30, 30),
+        TileType::Minicannon => "• ".truecolor(30, 30, 30),
         TileType::Empty => "□ ".white(),
         _ => "? ".bold().bright_magenta(),
     }
 }
+
+#[cfg(test)]
+fn test_world() -> World {
+    World {
+        tiles: vec![TileType::Plains; 6],
+        width: 3,
+        height: 2,
+        units: Vec::new(),
+    }
+}
+
+#[test]
+fn test_headless_draws_nothing() {
+    let mut visualiser = Visualiser::headless();
+    let world = test_world();
+    visualiser.on_tiles_changed(&world, &vec![(0, TileType::Road)]);
+    visualiser.on_step_end("fill", &world);
+    visualiser.on_complete(&world);
+    assert_eq!(visualiser.last_frame.get(), None);
+}
+
+#[test]
+fn test_step_boundaries_skip_partial_frames() {
+    let mut visualiser = Visualiser::new()
+        .without_frame_limit()
+        .with_render_mode(RenderMode::StepBoundaries);
+    let world = test_world();
+    visualiser.on_tiles_changed(&world, &vec![(0, TileType::Road)]);
+    assert_eq!(visualiser.last_frame.get(), None);
+    visualiser.on_step_end("join_headquarters", &world);
+    assert!(visualiser.last_frame.get().is_some());
+}
+
+#[test]
+fn test_frame_rate_spaces_frames() {
+    let visualiser = Visualiser::new().with_frame_rate(20);
+    let world = test_world();
+    let start = Instant::now();
+    visualiser.visualise(&world);
+    visualiser.visualise(&world);
+    visualiser.visualise(&world);
+    // Only the frames after the first wait.
+    assert!(start.elapsed() >= Duration::from_millis(100));
+}
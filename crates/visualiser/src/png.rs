@@ -0,0 +1,183 @@
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::Path;
+
+use world_core::{Player, TileDiff, TileType, Unit, World};
+
+use crate::heatmap::{gradient, Heatmap, Rgb, LEGEND_STEPS};
+use crate::{player_rgb, VisualiserError};
+
+const TILE_SIZE: u32 = 16;
+const LEGEND_HEIGHT: u32 = 12;
+/// How much of the heatmap colour shows through, with the tile underneath.
+const HEATMAP_OPACITY: f32 = 0.7;
+const DIFF_GAP: u32 = 8;
+const DIFF_BORDER: u32 = 2;
+const DIFF_HIGHLIGHT: Rgb = (255, 0, 255);
+/// Units are drawn as a square in their owner's colour, this far in from the
+/// edge of the tile, with a black outline.
+const UNIT_INSET: u32 = 4;
+
+pub fn save_png(
+    path: &Path,
+    world: &World,
+    heatmap: Option<&Heatmap>,
+) -> Result<(), VisualiserError> {
+    if let Some(heatmap) = heatmap {
+        heatmap.check_size(world)?;
+    }
+    let width = world.width * TILE_SIZE;
+    let map_height = world.height * TILE_SIZE;
+    let height = map_height + heatmap.map_or(0, |_| LEGEND_HEIGHT);
+    let range = heatmap.and_then(Heatmap::range);
+
+    let mut pixels = Vec::with_capacity((width * height * 3) as usize);
+    for y in 0..height {
+        for x in 0..width {
+            let (r, g, b) = if y < map_height {
+                let i = ((y / TILE_SIZE) * world.width + x / TILE_SIZE) as usize;
+                let colour = match world.unit_at(i) {
+                    Some(unit) => unit_colour(unit, x % TILE_SIZE, y % TILE_SIZE)
+                        .unwrap_or_else(|| tile_colour(world.tile_at(i))),
+                    None => tile_colour(world.tile_at(i)),
+                };
+                match heatmap {
+                    Some(heatmap) => blend(colour, heatmap.colour_at(i, range)),
+                    None => colour,
+                }
+            } else {
+                let step = x * LEGEND_STEPS as u32 / width;
+                gradient(step as f32 / (LEGEND_STEPS - 1) as f32)
+            };
+            pixels.extend([r, g, b]);
+        }
+    }
+
+    let legend = heatmap
+        .zip(range)
+        .map(|(heatmap, (min, max))| format!("{}: {} to {}", heatmap.name, min, max));
+    encode(path, width, height, &pixels, legend)
+}
+
+/// Saves `before` and `after` next to each other, with changed tiles outlined
+/// on both sides.
+pub fn save_diff_png(
+    path: &Path,
+    before: &World,
+    after: &World,
+    diffs: &[TileDiff],
+) -> Result<(), VisualiserError> {
+    let changed: Vec<usize> = diffs
+        .iter()
+        .map(|diff| before.index_from_coords(&diff.coords))
+        .collect();
+    let map_width = before.width * TILE_SIZE;
+    let width = map_width * 2 + DIFF_GAP;
+    let height = before.height * TILE_SIZE;
+
+    let mut pixels = Vec::with_capacity((width * height * 3) as usize);
+    for y in 0..height {
+        for x in 0..width {
+            let (world, x) = match x {
+                x if x < map_width => (before, x),
+                x if x >= map_width + DIFF_GAP => (after, x - map_width - DIFF_GAP),
+                _ => {
+                    pixels.extend([0, 0, 0]);
+                    continue;
+                }
+            };
+            let i = ((y / TILE_SIZE) * world.width + x / TILE_SIZE) as usize;
+            let on_border = [x % TILE_SIZE, y % TILE_SIZE]
+                .iter()
+                .any(|offset| *offset < DIFF_BORDER || *offset >= TILE_SIZE - DIFF_BORDER);
+            let (r, g, b) = if on_border && changed.contains(&i) {
+                DIFF_HIGHLIGHT
+            } else {
+                tile_colour(world.tile_at(i))
+            };
+            pixels.extend([r, g, b]);
+        }
+    }
+    encode(path, width, height, &pixels, None)
+}
+
+fn encode(
+    path: &Path,
+    width: u32,
+    height: u32,
+    pixels: &[u8],
+    legend: Option<String>,
+) -> Result<(), VisualiserError> {
+    let file = BufWriter::new(File::create(path)?);
+    let mut encoder = png::Encoder::new(file, width, height);
+    encoder.set_color(png::ColorType::Rgb);
+    encoder.set_depth(png::BitDepth::Eight);
+    if let Some(legend) = legend {
+        encoder.add_text_chunk("Legend".to_string(), legend)?;
+    }
+    let mut writer = encoder.write_header()?;
+    writer.write_image_data(pixels)?;
+    Ok(())
+}
+
+fn blend(tile: Rgb, overlay: Rgb) -> Rgb {
+    let mix =
+        |a: u8, b: u8| (a as f32 * (1.0 - HEATMAP_OPACITY) + b as f32 * HEATMAP_OPACITY) as u8;
+    (
+        mix(tile.0, overlay.0),
+        mix(tile.1, overlay.1),
+        mix(tile.2, overlay.2),
+    )
+}
+
+fn player_colour(player: &Option<Player>) -> Rgb {
+    match player {
+        Some(player) => player_rgb(*player),
+        None => (200, 200, 200),
+    }
+}
+
+/// The colour of a unit at an offset within its tile, or `None` outside the
+/// unit's square.
+fn unit_colour(unit: &Unit, x: u32, y: u32) -> Option<Rgb> {
+    let range = UNIT_INSET..TILE_SIZE - UNIT_INSET;
+    if !range.contains(&x) || !range.contains(&y) {
+        return None;
+    }
+    let edges = [UNIT_INSET, TILE_SIZE - UNIT_INSET - 1];
+    if edges.contains(&x) || edges.contains(&y) {
+        return Some((0, 0, 0));
+    }
+    Some(player_colour(&Some(unit.owner)))
+}
+
+fn tile_colour(tile_type: &TileType) -> Rgb {
+    match tile_type {
+        TileType::Plains => (120, 200, 80),
+        TileType::Mountain => (200, 180, 60),
+        TileType::Sea => (40, 80, 200),
+        TileType::Shoal => (230, 210, 150),
+        TileType::City(player) => player_colour(player),
+        TileType::Road => (90, 90, 90),
+        TileType::Forest => (0, 90, 0),
+        TileType::Factory(player) => player_colour(player),
+        TileType::Hq(player) => player_colour(&Some(*player)),
+        TileType::Wasteland => (150, 120, 70),
+        TileType::Ruins => (140, 140, 140),
+        TileType::RoughSea => (20, 30, 120),
+        TileType::Mist => (190, 200, 220),
+        TileType::Fire => (230, 60, 20),
+        TileType::Meteor => (140, 60, 200),
+        TileType::Plasma => (230, 60, 230),
+        TileType::ComTower(player)
+        | TileType::TempAirport(player)
+        | TileType::TempPort(player)
+        | TileType::Lab(player) => player_colour(player),
+        TileType::Pipe => (70, 70, 80),
+        TileType::PipeSeam => (110, 110, 120),
+        TileType::MissileSilo => (200, 200, 200),
+        TileType::BlackCannon | TileType::Laser | TileType::Minicannon => (30, 30, 30),
+        TileType::Empty => (255, 255, 255),
+        _ => (255, 0, 255),
+    }
+}
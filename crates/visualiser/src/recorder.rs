@@ -0,0 +1,41 @@
+use world_core::{GenerationObserver, TileCollection, World};
+
+pub struct Frame {
+    /// The step that was running when the frame was captured.
+    pub step: String,
+    pub world: World,
+}
+
+/// Keeps a copy of the world after every step and tile change.
+#[derive(Default)]
+pub struct Recorder {
+    pub frames: Vec<Frame>,
+    current_step: String,
+}
+
+impl Recorder {
+    pub fn new() -> Recorder {
+        Recorder::default()
+    }
+
+    fn capture(&mut self, world: World) {
+        self.frames.push(Frame {
+            step: self.current_step.clone(),
+            world,
+        });
+    }
+}
+
+impl GenerationObserver for Recorder {
+    fn on_step_start(&mut self, step: &str, _world: &World) {
+        self.current_step = step.to_string();
+    }
+    fn on_step_end(&mut self, _step: &str, world: &World) {
+        self.capture(world.clone());
+    }
+    fn on_tiles_changed(&mut self, world: &World, changes: &TileCollection) {
+        let mut world = world.clone();
+        world.apply_tile_collection(changes);
+        self.capture(world);
+    }
+}
@@ -0,0 +1,61 @@
+use crate::{Coordinates, TileType, World, WorldError};
+
+/// A tile that differs between two worlds of the same size.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TileDiff {
+    pub coords: Coordinates,
+    pub before: TileType,
+    pub after: TileType,
+}
+
+impl World {
+    /// Lists every tile that differs between `self` and `other`, in index
+    /// order.
+    pub fn diff(&self, other: &World) -> Result<Vec<TileDiff>, WorldError> {
+        if (self.width, self.height) != (other.width, other.height) {
+            return Err(WorldError::SizeMismatch {
+                before: (self.width, self.height),
+                after: (other.width, other.height),
+            });
+        }
+        let diffs = self
+            .tiles
+            .iter()
+            .zip(&other.tiles)
+            .enumerate()
+            .filter(|(_, (before, after))| before != after)
+            .map(|(i, (before, after))| TileDiff {
+                coords: self.coords_from_index(i),
+                before: *before,
+                after: *after,
+            })
+            .collect();
+        Ok(diffs)
+    }
+}
+
+#[test]
+fn test_diff() {
+    let before = World {
+        tiles: vec![TileType::Plains; 4],
+        width: 2,
+        height: 2,
+        units: Vec::new(),
+    };
+    let mut after = before.clone();
+    after.tiles[3] = TileType::Forest;
+
+    let diffs = before.diff(&after).unwrap();
+    assert_eq!(
+        diffs,
+        vec![TileDiff {
+            coords: Coordinates::new(1, 1),
+            before: TileType::Plains,
+            after: TileType::Forest,
+        }]
+    );
+
+    after.width = 4;
+    after.height = 1;
+    assert!(before.diff(&after).is_err());
+}
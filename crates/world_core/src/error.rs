@@ -0,0 +1,34 @@
+use crate::{Player, TileType, TileTypeId};
+
+#[derive(Debug, Clone, PartialEq, thiserror::Error)]
+pub enum WorldError {
+    #[error("coordinates ({x}, {y}) are outside a world of size ({width}, {height})")]
+    OutOfBounds {
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+    },
+    #[error("tile index {index} is outside a world of {size} tiles")]
+    IndexOutOfBounds { index: usize, size: usize },
+    #[error("unknown tile id {0}")]
+    UnknownTileId(TileTypeId),
+    #[error("tile id {id} cannot have allegiance {allegiance:?}")]
+    InvalidAllegiance {
+        id: TileTypeId,
+        allegiance: Option<Player>,
+    },
+    #[error("unknown unit id {0}")]
+    UnknownUnitId(usize),
+    #[error("no id for tile {0:?}")]
+    NoTileId(TileType),
+    #[error("invalid map cell {0:?}")]
+    InvalidMapCell(String),
+    #[error("cannot compare a {before:?} world with a {after:?} world")]
+    SizeMismatch {
+        before: (u32, u32),
+        after: (u32, u32),
+    },
+    #[error("malformed map: {0}")]
+    MalformedMap(String),
+}
@@ -1,14 +1,29 @@
+mod diff;
+mod error;
+pub mod map_format;
+mod movement;
+mod observer;
 pub mod parser;
 mod tile_type;
+mod tileset;
+mod unit;
 use std::f32::consts::PI;
 
+pub use diff::TileDiff;
+pub use error::WorldError;
+pub use movement::*;
+pub use observer::GenerationObserver;
 pub use tile_type::*;
+pub use tileset::Tileset;
+pub use unit::*;
 
 #[derive(Clone)]
 pub struct World {
     pub tiles: Vec<TileType>,
     pub width: u32,
     pub height: u32,
+    /// Pre-deployed units, one entry per tile. Empty when there are none.
+    pub units: Vec<Option<Unit>>,
 }
 
 pub type Neighbours = [Option<Coordinates>; 4];
@@ -68,13 +83,20 @@ impl Coordinates {
 
 impl World {
     pub fn index_from_coords(&self, coords: &Coordinates) -> usize {
+        self.try_index_from_coords(coords)
+            .unwrap_or_else(|err| panic!("{}", err))
+    }
+
+    pub fn try_index_from_coords(&self, coords: &Coordinates) -> Result<usize, WorldError> {
         if coords.x >= self.width || coords.y >= self.height {
-            panic!(
-                "Trying to read coords ({}, {}) for world of size ({},{})",
-                coords.x, coords.y, self.width, self.height
-            )
+            return Err(WorldError::OutOfBounds {
+                x: coords.x,
+                y: coords.y,
+                width: self.width,
+                height: self.height,
+            });
         }
-        (coords.y * self.width + coords.x) as usize
+        Ok((coords.y * self.width + coords.x) as usize)
     }
 
     pub fn coords_from_index(&self, i: usize) -> Coordinates {
@@ -104,15 +126,55 @@ impl World {
         return [up, down, left, right];
     }
 
+    /// All tiles touching `coords`, including diagonals.
+    pub fn get_surrounding(&self, &Coordinates { x, y }: &Coordinates) -> Vec<Coordinates> {
+        let mut surrounding = Vec::with_capacity(8);
+        for dy in -1..=1 {
+            for dx in -1..=1 {
+                let (nx, ny) = (x as i32 + dx, y as i32 + dy);
+                let in_bounds =
+                    nx >= 0 && ny >= 0 && (nx as u32) < self.width && (ny as u32) < self.height;
+                if (dx, dy) != (0, 0) && in_bounds {
+                    surrounding.push(Coordinates::new(nx as u32, ny as u32));
+                }
+            }
+        }
+        surrounding
+    }
+
     pub fn update_tile(&mut self, coords: &Coordinates, tile_type: TileType) {
         let i = self.index_from_coords(coords);
         self.tiles[i] = tile_type;
     }
 
+    pub fn try_update_tile(
+        &mut self,
+        coords: &Coordinates,
+        tile_type: TileType,
+    ) -> Result<(), WorldError> {
+        let i = self.try_index_from_coords(coords)?;
+        self.tiles[i] = tile_type;
+        Ok(())
+    }
+
     pub fn update_tile_by_index(&mut self, index: usize, tile_type: TileType) {
         self.tiles[index] = tile_type;
     }
 
+    pub fn try_update_tile_by_index(
+        &mut self,
+        index: usize,
+        tile_type: TileType,
+    ) -> Result<(), WorldError> {
+        let size = self.tiles.len();
+        let tile = self
+            .tiles
+            .get_mut(index)
+            .ok_or(WorldError::IndexOutOfBounds { index, size })?;
+        *tile = tile_type;
+        Ok(())
+    }
+
     pub fn apply_tile_collection(&mut self, tile_collection: &TileCollection) {
         for (index, tile_type) in tile_collection {
             self.update_tile_by_index(*index, *tile_type)
@@ -126,6 +188,29 @@ impl World {
         let i = self.index_from_coords(coords);
         return &self.tiles[i];
     }
+
+    pub fn try_tile_at(&self, i: usize) -> Result<&TileType, WorldError> {
+        self.tiles.get(i).ok_or(WorldError::IndexOutOfBounds {
+            index: i,
+            size: self.tiles.len(),
+        })
+    }
+    pub fn try_tile_at_coords(&self, coords: &Coordinates) -> Result<&TileType, WorldError> {
+        let i = self.try_index_from_coords(coords)?;
+        Ok(&self.tiles[i])
+    }
+
+    pub fn unit_at(&self, i: usize) -> Option<&Unit> {
+        self.units.get(i).and_then(Option::as_ref)
+    }
+
+    /// Puts `unit` on tile `i`, or clears the tile if it is `None`.
+    pub fn place_unit(&mut self, i: usize, unit: Option<Unit>) {
+        if self.units.len() < self.tiles.len() {
+            self.units.resize(self.tiles.len(), None);
+        }
+        self.units[i] = unit;
+    }
 }
 
 #[derive(Clone, serde::Deserialize, Debug)]
@@ -135,17 +220,119 @@ pub struct TileData {
     pub colour: String,
 }
 
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
 pub enum Players {
     Two,
-    _Three,
-    _Four,
+    Three,
+    Four,
 }
 
-#[derive(Copy, Clone)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
 pub enum Symmetry {
+    /// Half-turn rotation about the centre of the map.
     Rotational,
+    /// Mirrored across the horizontal centre line, top to bottom.
     Horizontal,
+    /// Mirrored across the vertical centre line, left to right.
     Vertical,
+    /// Mirrored across the line from the top left to the bottom right corner.
+    /// Square maps only.
+    Diagonal,
+    /// Mirrored across the line from the top right to the bottom left corner.
+    /// Square maps only.
+    AntiDiagonal,
+    /// No symmetry. Every tile is its own reciprocal.
+    None,
+}
+
+impl Symmetry {
+    pub fn supports(&self, width: u32, height: u32) -> bool {
+        match self {
+            Symmetry::Diagonal | Symmetry::AntiDiagonal => width == height,
+            _ => true,
+        }
+    }
+
+    /// The tile that mirrors `coords` in `world` under this symmetry. The
+    /// world must be one the symmetry `supports`.
+    pub fn reciprocal(&self, world: &World, coords: &Coordinates) -> Coordinates {
+        match self {
+            Symmetry::Rotational => {
+                let x = world.width - coords.x - 1;
+                let y = world.height - coords.y - 1;
+                Coordinates::new(x, y)
+            }
+            Symmetry::Horizontal => Coordinates::new(coords.x, world.height - coords.y - 1),
+            Symmetry::Vertical => Coordinates::new(world.width - coords.x - 1, coords.y),
+            Symmetry::Diagonal => Coordinates::new(coords.y, coords.x),
+            Symmetry::AntiDiagonal => {
+                Coordinates::new(world.width - coords.y - 1, world.height - coords.x - 1)
+            }
+            Symmetry::None => *coords,
+        }
+    }
+}
+
+#[test]
+fn test_reciprocal_is_an_involution() {
+    for symmetry in [
+        Symmetry::Rotational,
+        Symmetry::Horizontal,
+        Symmetry::Vertical,
+        Symmetry::Diagonal,
+        Symmetry::AntiDiagonal,
+    ] {
+        for width in 1..=12 {
+            for height in 1..=12 {
+                if !symmetry.supports(width, height) {
+                    continue;
+                }
+                let world = World {
+                    tiles: vec![TileType::Empty; (width * height) as usize],
+                    width,
+                    height,
+                    units: Vec::new(),
+                };
+                for i in 0..world.tiles.len() {
+                    let coords = world.coords_from_index(i);
+                    let reciprocal = symmetry.reciprocal(&world, &coords);
+                    assert!(world.try_index_from_coords(&reciprocal).is_ok());
+                    assert_eq!(symmetry.reciprocal(&world, &reciprocal), coords);
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn test_diagonal_reciprocals() {
+    let world = World {
+        tiles: vec![TileType::Empty; 25],
+        width: 5,
+        height: 5,
+        units: Vec::new(),
+    };
+    let reciprocal =
+        |symmetry: Symmetry, x, y| symmetry.reciprocal(&world, &Coordinates::new(x, y));
+    assert_eq!(reciprocal(Symmetry::Diagonal, 1, 3), Coordinates::new(3, 1));
+    assert_eq!(
+        reciprocal(Symmetry::AntiDiagonal, 0, 0),
+        Coordinates::new(4, 4)
+    );
+    assert_eq!(
+        reciprocal(Symmetry::AntiDiagonal, 1, 0),
+        Coordinates::new(4, 3)
+    );
+    // Only tiles on the mirror line are their own reciprocal.
+    for i in 0..world.tiles.len() {
+        let coords = world.coords_from_index(i);
+        let (x, y) = (coords.x, coords.y);
+        assert_eq!(reciprocal(Symmetry::Diagonal, x, y) == coords, x == y);
+        assert_eq!(
+            reciprocal(Symmetry::AntiDiagonal, x, y) == coords,
+            x + y == 4
+        );
+    }
 }
 
 #[test]
@@ -0,0 +1,180 @@
+//! Plain-text map files.
+//!
+//! A map is one line per row of tiles, with each tile written as its id from
+//! `data/tiles.csv`. Owned properties append the owning player's number after
+//! a colon, so `8:1` is player one's headquarters and `6` is a neutral city.
+//!
+//! A unit standing on a tile follows an `@` as its id, owner and, if it isn't
+//! at full health, its HP: `1@4:2` is player two's tank on plains and
+//! `6:1@0:1:7` is player one's infantry with 7 HP on their own city.
+
+use crate::{Player, TileType, Unit, UnitType, World, WorldError, MAX_HP};
+
+pub fn read_map(text: &str) -> Result<World, WorldError> {
+    let mut reader = csv::ReaderBuilder::new()
+        .has_headers(false)
+        .trim(csv::Trim::All)
+        .from_reader(text.as_bytes());
+
+    let mut tiles = Vec::new();
+    let mut units = Vec::new();
+    let mut width = 0;
+    let mut height = 0;
+    for record in reader.records() {
+        let record = record.map_err(|err| WorldError::MalformedMap(err.to_string()))?;
+        if record.iter().all(|cell| cell.is_empty()) {
+            continue;
+        }
+        width = record.len() as u32;
+        height += 1;
+        for cell in record.iter() {
+            let (tile, unit) = match cell.split_once('@') {
+                Some((tile, unit)) => (tile, Some(parse_unit(unit)?)),
+                None => (cell, None),
+            };
+            tiles.push(parse_tile(tile)?);
+            units.push(unit);
+        }
+    }
+    if units.iter().all(Option::is_none) {
+        units.clear();
+    }
+
+    Ok(World {
+        tiles,
+        width,
+        height,
+        units,
+    })
+}
+
+pub fn write_map(world: &World) -> String {
+    let mut output = String::new();
+    for (y, row) in world.tiles.chunks(world.width as usize).enumerate() {
+        let cells: Vec<String> = row
+            .iter()
+            .enumerate()
+            .map(|(x, tile)| {
+                let i = y * world.width as usize + x;
+                match world.unit_at(i) {
+                    Some(unit) => format!("{}@{}", format_tile(tile), format_unit(unit)),
+                    None => format_tile(tile),
+                }
+            })
+            .collect();
+        output.push_str(&cells.join(","));
+        output.push('\n');
+    }
+    output
+}
+
+fn parse_tile(cell: &str) -> Result<TileType, WorldError> {
+    let invalid = || WorldError::InvalidMapCell(cell.to_string());
+    let (id, player) = match cell.split_once(':') {
+        Some((id, player)) => (id, Some(player)),
+        None => (cell, None),
+    };
+    let id = id.parse().map_err(|_| invalid())?;
+    let allegiance = match player {
+        Some(player) => Some(
+            player
+                .parse()
+                .ok()
+                .and_then(Player::from_number)
+                .ok_or_else(invalid)?,
+        ),
+        None => None,
+    };
+    TileType::try_from_id(id, allegiance)
+}
+
+fn parse_unit(cell: &str) -> Result<Unit, WorldError> {
+    let invalid = || WorldError::InvalidMapCell(cell.to_string());
+    let mut parts = cell.split(':');
+    let id = parts
+        .next()
+        .and_then(|id| id.parse().ok())
+        .ok_or_else(invalid)?;
+    let owner = parts
+        .next()
+        .and_then(|player| player.parse().ok())
+        .and_then(Player::from_number)
+        .ok_or_else(invalid)?;
+    let hp = match parts.next() {
+        Some(hp) => hp
+            .parse()
+            .ok()
+            .filter(|hp| (1..=MAX_HP).contains(hp))
+            .ok_or_else(invalid)?,
+        None => MAX_HP,
+    };
+    if parts.next().is_some() {
+        return Err(invalid());
+    }
+    Ok(Unit {
+        unit_type: UnitType::try_from_id(id)?,
+        owner,
+        hp,
+    })
+}
+
+fn format_unit(unit: &Unit) -> String {
+    let unit_id = format!("{}:{}", unit.unit_type.id(), unit.owner.number());
+    if unit.hp == MAX_HP {
+        unit_id
+    } else {
+        format!("{}:{}", unit_id, unit.hp)
+    }
+}
+
+fn format_tile(tile_type: &TileType) -> String {
+    match tile_type.owner() {
+        Some(player) => format!("{}:{}", tile_type.to_id(), player.number()),
+        None => tile_type.to_id().to_string(),
+    }
+}
+
+#[test]
+fn test_map_round_trip() {
+    let text = "1,1,8:1\n2,6,3\n6:2,4,5\n";
+    let world = read_map(text).unwrap();
+    assert_eq!(world.width, 3);
+    assert_eq!(world.height, 3);
+    assert_eq!(world.tiles[2], TileType::Hq(Player::One));
+    assert_eq!(world.tiles[4], TileType::City(None));
+    assert_eq!(world.tiles[6], TileType::City(Some(Player::Two)));
+    assert_eq!(write_map(&world), text);
+}
+
+#[test]
+fn test_units_round_trip() {
+    let text = "1@4:2,6:1@0:1:7\n";
+    let world = read_map(text).unwrap();
+    assert_eq!(
+        world.unit_at(0),
+        Some(&Unit::new(UnitType::Tank, Player::Two))
+    );
+    assert_eq!(world.unit_at(1).map(|unit| unit.hp), Some(7));
+    assert_eq!(write_map(&world), text);
+    assert!(read_map("1,1\n").unwrap().units.is_empty());
+    assert!(read_map("1@0:1:11\n").is_err());
+}
+
+#[test]
+fn test_read_map_errors() {
+    assert_eq!(
+        read_map("1,1\n1,x\n").err(),
+        Some(WorldError::InvalidMapCell("x".to_string()))
+    );
+    assert_eq!(
+        read_map("1,8\n").err(),
+        Some(WorldError::InvalidAllegiance {
+            id: 8,
+            allegiance: None
+        })
+    );
+    assert!(matches!(
+        read_map("1,1\n1\n"),
+        Err(WorldError::MalformedMap(_))
+    ));
+}
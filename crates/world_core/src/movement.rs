@@ -0,0 +1,48 @@
+use crate::TileType;
+
+/// How a unit moves over terrain, following the Advance Wars movement classes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MovementType {
+    Foot,
+    Tires,
+    Treads,
+}
+
+impl TileType {
+    /// Movement points needed to enter this tile, or `None` if it is
+    /// impassable. `Empty` tiles are treated as plains so unfinished maps can
+    /// still be measured.
+    pub fn movement_cost(&self, movement: MovementType) -> Option<u32> {
+        match (self, movement) {
+            (
+                TileType::Sea
+                | TileType::_Reef
+                | TileType::RoughSea
+                | TileType::Mist
+                | TileType::Fire
+                | TileType::Meteor
+                | TileType::Plasma
+                | TileType::Pipe
+                | TileType::PipeSeam
+                | TileType::BlackCannon
+                | TileType::Laser
+                | TileType::Minicannon,
+                _,
+            ) => None,
+            (TileType::Mountain, MovementType::Foot) => Some(2),
+            (TileType::Mountain, _) => None,
+            (TileType::Plains | TileType::Empty | TileType::MissileSilo, MovementType::Tires) => {
+                Some(2)
+            }
+            (TileType::Forest, MovementType::Tires) => Some(3),
+            (TileType::Forest, MovementType::Treads) => Some(2),
+            (TileType::Wasteland, MovementType::Tires) => Some(3),
+            (TileType::Wasteland, MovementType::Treads) => Some(2),
+            _ => Some(1),
+        }
+    }
+
+    pub fn is_passable(&self, movement: MovementType) -> bool {
+        self.movement_cost(movement).is_some()
+    }
+}
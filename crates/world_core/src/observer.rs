@@ -0,0 +1,32 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use crate::{TileCollection, World};
+
+/// Receives events while a `World` is generated. Every callback does nothing
+/// by default, so observers only implement the events they care about.
+pub trait GenerationObserver {
+    fn on_step_start(&mut self, _step: &str, _world: &World) {}
+    fn on_step_end(&mut self, _step: &str, _world: &World) {}
+    /// Tiles a step has decided on but not yet written to `world`, such as a
+    /// path in progress.
+    fn on_tiles_changed(&mut self, _world: &World, _changes: &TileCollection) {}
+    fn on_complete(&mut self, _world: &World) {}
+}
+
+/// Lets callers keep a handle to an observer after handing it to a builder,
+/// e.g. to read a recording once generation is done.
+impl<T: GenerationObserver> GenerationObserver for Rc<RefCell<T>> {
+    fn on_step_start(&mut self, step: &str, world: &World) {
+        self.borrow_mut().on_step_start(step, world)
+    }
+    fn on_step_end(&mut self, step: &str, world: &World) {
+        self.borrow_mut().on_step_end(step, world)
+    }
+    fn on_tiles_changed(&mut self, world: &World, changes: &TileCollection) {
+        self.borrow_mut().on_tiles_changed(world, changes)
+    }
+    fn on_complete(&mut self, world: &World) {
+        self.borrow_mut().on_complete(world)
+    }
+}
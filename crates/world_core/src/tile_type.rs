@@ -1,9 +1,11 @@
+use crate::WorldError;
+
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
 pub enum Player {
     One,
     Two,
-    _Three,
-    _Four,
+    Three,
+    Four,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Copy)]
@@ -19,23 +21,73 @@ pub enum TileType {
     _Airport(Option<Player>),
     Hq(Player),
     _Reef,
+    Shoal,
+    // Days of Ruin tiles.
+    Wasteland,
+    Ruins,
+    RoughSea,
+    Mist,
+    Fire,
+    Meteor,
+    Plasma,
+    ComTower(Option<Player>),
+    TempAirport(Option<Player>),
+    TempPort(Option<Player>),
+    // Black Hole structures from Advance Wars 2 and DS.
+    Pipe,
+    PipeSeam,
+    MissileSilo,
+    BlackCannon,
+    Laser,
+    Minicannon,
+    Lab(Option<Player>),
     Empty,
 }
 
 pub type TileTypeId = usize;
 
+impl Player {
+    pub fn from_number(number: u32) -> Option<Player> {
+        match number {
+            1 => Some(Player::One),
+            2 => Some(Player::Two),
+            3 => Some(Player::Three),
+            4 => Some(Player::Four),
+            _ => None,
+        }
+    }
+
+    pub fn number(&self) -> u32 {
+        match self {
+            Player::One => 1,
+            Player::Two => 2,
+            Player::Three => 3,
+            Player::Four => 4,
+        }
+    }
+}
+
 impl TileType {
     pub fn from_id(id: TileTypeId, allegiance: Option<Player>) -> TileType {
+        TileType::try_from_id(id, allegiance).unwrap_or_else(|err| panic!("{}", err))
+    }
+
+    pub fn try_from_id(id: TileTypeId, allegiance: Option<Player>) -> Result<TileType, WorldError> {
         if let Some(player) = allegiance {
             return match id {
-                6 => TileType::City(Some(player)),
-                7 => TileType::Factory(Some(player)),
-                8 => TileType::Hq(player),
-                _ => panic!("Unknown id: {:?}", id),
+                6 => Ok(TileType::City(Some(player))),
+                7 => Ok(TileType::Factory(Some(player))),
+                8 => Ok(TileType::Hq(player)),
+                17 => Ok(TileType::ComTower(Some(player))),
+                18 => Ok(TileType::TempAirport(Some(player))),
+                19 => Ok(TileType::TempPort(Some(player))),
+                26 => Ok(TileType::Lab(Some(player))),
+                _ => Err(WorldError::InvalidAllegiance { id, allegiance }),
             };
         }
 
-        return match id {
+        let tile_type = match id {
+            0 => TileType::Empty,
             1 => TileType::Plains,
             2 => TileType::Sea,
             3 => TileType::Forest,
@@ -43,12 +95,36 @@ impl TileType {
             5 => TileType::Road,
             6 => TileType::City(allegiance),
             7 => TileType::Factory(allegiance),
-            _ => panic!("Unknown id: {:?}", id),
+            9 => TileType::Shoal,
+            10 => TileType::Wasteland,
+            11 => TileType::Ruins,
+            12 => TileType::RoughSea,
+            13 => TileType::Mist,
+            14 => TileType::Fire,
+            15 => TileType::Meteor,
+            16 => TileType::Plasma,
+            17 => TileType::ComTower(allegiance),
+            18 => TileType::TempAirport(allegiance),
+            19 => TileType::TempPort(allegiance),
+            20 => TileType::Pipe,
+            21 => TileType::PipeSeam,
+            22 => TileType::MissileSilo,
+            23 => TileType::BlackCannon,
+            24 => TileType::Laser,
+            25 => TileType::Minicannon,
+            26 => TileType::Lab(allegiance),
+            8 => return Err(WorldError::InvalidAllegiance { id, allegiance }),
+            _ => return Err(WorldError::UnknownTileId(id)),
         };
+        Ok(tile_type)
     }
 
     pub fn to_id(&self) -> TileTypeId {
-        match self {
+        self.try_to_id().unwrap_or_else(|err| panic!("{}", err))
+    }
+
+    pub fn try_to_id(&self) -> Result<TileTypeId, WorldError> {
+        let id = match self {
             TileType::Plains => 1,
             TileType::Sea => 2,
             TileType::Forest => 3,
@@ -57,10 +133,60 @@ impl TileType {
             TileType::City(_) => 6,
             TileType::Factory(_) => 7,
             TileType::Hq(_) => 8,
-            _ => panic!("No id for tile {:?}", self),
+            TileType::Shoal => 9,
+            TileType::Wasteland => 10,
+            TileType::Ruins => 11,
+            TileType::RoughSea => 12,
+            TileType::Mist => 13,
+            TileType::Fire => 14,
+            TileType::Meteor => 15,
+            TileType::Plasma => 16,
+            TileType::ComTower(_) => 17,
+            TileType::TempAirport(_) => 18,
+            TileType::TempPort(_) => 19,
+            TileType::Pipe => 20,
+            TileType::PipeSeam => 21,
+            TileType::MissileSilo => 22,
+            TileType::BlackCannon => 23,
+            TileType::Laser => 24,
+            TileType::Minicannon => 25,
+            TileType::Lab(_) => 26,
+            TileType::Empty => 0,
+            _ => return Err(WorldError::NoTileId(*self)),
+        };
+        Ok(id)
+    }
+
+    pub fn owner(&self) -> Option<Player> {
+        match self {
+            TileType::City(player)
+            | TileType::Factory(player)
+            | TileType::_Port(player)
+            | TileType::_Airport(player)
+            | TileType::ComTower(player)
+            | TileType::TempAirport(player)
+            | TileType::TempPort(player)
+            | TileType::Lab(player) => *player,
+            TileType::Hq(player) => Some(*player),
+            _ => None,
         }
     }
 
+    pub fn is_property(&self) -> bool {
+        matches!(
+            self,
+            TileType::City(_)
+                | TileType::Factory(_)
+                | TileType::_Port(_)
+                | TileType::_Airport(_)
+                | TileType::ComTower(_)
+                | TileType::TempAirport(_)
+                | TileType::TempPort(_)
+                | TileType::Lab(_)
+                | TileType::Hq(_)
+        )
+    }
+
     pub fn is_empty(&self) -> bool {
         match self {
             TileType::Empty => true,
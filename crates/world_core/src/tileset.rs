@@ -0,0 +1,83 @@
+use crate::TileType;
+
+/// The game a map is generated for, which decides the tiles it may use.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Tileset {
+    /// Advance Wars 1, 2 and DS.
+    #[default]
+    AdvanceWars,
+    /// Advance Wars: Days of Ruin, which adds wasteland, ruins and hazards to
+    /// the Advance Wars tiles but has no Black Hole structures.
+    DaysOfRuin,
+}
+
+impl Tileset {
+    pub fn contains(&self, tile_type: &TileType) -> bool {
+        match self {
+            Tileset::AdvanceWars => !tile_type.is_days_of_ruin(),
+            Tileset::DaysOfRuin => !tile_type.is_black_hole(),
+        }
+    }
+}
+
+impl TileType {
+    pub fn is_days_of_ruin(&self) -> bool {
+        matches!(
+            self,
+            TileType::Wasteland
+                | TileType::Ruins
+                | TileType::RoughSea
+                | TileType::Mist
+                | TileType::Fire
+                | TileType::Meteor
+                | TileType::Plasma
+                | TileType::TempAirport(_)
+                | TileType::TempPort(_)
+        )
+    }
+
+    pub fn is_black_hole(&self) -> bool {
+        matches!(
+            self,
+            TileType::Pipe
+                | TileType::PipeSeam
+                | TileType::MissileSilo
+                | TileType::BlackCannon
+                | TileType::Laser
+                | TileType::Minicannon
+        )
+    }
+}
+
+#[test]
+fn test_tileset_ids_round_trip() {
+    use crate::Player;
+
+    for id in (10..=16).chain([18, 19]) {
+        let tile_type = TileType::from_id(id, None);
+        assert!(tile_type.is_days_of_ruin());
+        assert!(!Tileset::AdvanceWars.contains(&tile_type));
+        assert!(Tileset::DaysOfRuin.contains(&tile_type));
+        assert_eq!(tile_type.to_id(), id);
+    }
+    assert_eq!(
+        TileType::from_id(17, Some(Player::Two)),
+        TileType::ComTower(Some(Player::Two))
+    );
+    // Com towers and labs are shared by Dual Strike and Days of Ruin.
+    for tile_type in [TileType::ComTower(None), TileType::Lab(Some(Player::One))] {
+        assert!(Tileset::AdvanceWars.contains(&tile_type));
+        assert!(Tileset::DaysOfRuin.contains(&tile_type));
+        assert_eq!(
+            TileType::from_id(tile_type.to_id(), tile_type.owner()),
+            tile_type
+        );
+    }
+    for id in 20..=25 {
+        let tile_type = TileType::from_id(id, None);
+        assert!(tile_type.is_black_hole());
+        assert!(Tileset::AdvanceWars.contains(&tile_type));
+        assert!(!Tileset::DaysOfRuin.contains(&tile_type));
+        assert_eq!(tile_type.to_id(), id);
+    }
+}
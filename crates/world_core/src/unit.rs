@@ -0,0 +1,104 @@
+use crate::{MovementType, Player, WorldError};
+
+pub const MAX_HP: u8 = 10;
+
+/// Ground units from Advance Wars, with their ids for map files.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UnitType {
+    Infantry,
+    Mech,
+    Recon,
+    Apc,
+    Tank,
+    MediumTank,
+    Artillery,
+    Rockets,
+    AntiAir,
+    Missiles,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Unit {
+    pub unit_type: UnitType,
+    pub owner: Player,
+    pub hp: u8,
+}
+
+impl UnitType {
+    pub const ALL: [UnitType; 10] = [
+        UnitType::Infantry,
+        UnitType::Mech,
+        UnitType::Recon,
+        UnitType::Apc,
+        UnitType::Tank,
+        UnitType::MediumTank,
+        UnitType::Artillery,
+        UnitType::Rockets,
+        UnitType::AntiAir,
+        UnitType::Missiles,
+    ];
+
+    pub fn try_from_id(id: usize) -> Result<UnitType, WorldError> {
+        UnitType::ALL
+            .get(id)
+            .copied()
+            .ok_or(WorldError::UnknownUnitId(id))
+    }
+
+    pub fn id(&self) -> usize {
+        UnitType::ALL
+            .iter()
+            .position(|unit_type| unit_type == self)
+            .unwrap_or_default()
+    }
+
+    /// Price in funds at a factory.
+    pub fn cost(&self) -> u32 {
+        match self {
+            UnitType::Infantry => 1000,
+            UnitType::Mech => 3000,
+            UnitType::Recon => 4000,
+            UnitType::Apc => 5000,
+            UnitType::Tank => 7000,
+            UnitType::MediumTank => 16000,
+            UnitType::Artillery => 6000,
+            UnitType::Rockets => 15000,
+            UnitType::AntiAir => 8000,
+            UnitType::Missiles => 12000,
+        }
+    }
+
+    pub fn movement_type(&self) -> MovementType {
+        match self {
+            UnitType::Infantry | UnitType::Mech => MovementType::Foot,
+            UnitType::Recon | UnitType::Rockets | UnitType::Missiles => MovementType::Tires,
+            UnitType::Apc
+            | UnitType::Tank
+            | UnitType::MediumTank
+            | UnitType::Artillery
+            | UnitType::AntiAir => MovementType::Treads,
+        }
+    }
+}
+
+impl Unit {
+    /// A unit at full health.
+    pub fn new(unit_type: UnitType, owner: Player) -> Unit {
+        Unit {
+            unit_type,
+            owner,
+            hp: MAX_HP,
+        }
+    }
+}
+
+#[test]
+fn test_unit_ids_round_trip() {
+    for unit_type in UnitType::ALL {
+        assert_eq!(UnitType::try_from_id(unit_type.id()), Ok(unit_type));
+    }
+    assert_eq!(
+        UnitType::try_from_id(UnitType::ALL.len()),
+        Err(WorldError::UnknownUnitId(UnitType::ALL.len()))
+    );
+}
@@ -1,32 +1,152 @@
-use engine::{Config, WorldBuilder};
-use visualiser::Visualiser;
-use world_core::{Players, Symmetry, TileType};
+use engine::presets::Preset;
+use engine::properties::PropertyBudget;
+use engine::trace::GenerationTrace;
+use engine::{fields, Config, GenerationError, WorldBuilder};
+use visualiser::{Debugger, Heatmap, Visualiser};
+use world_core::{map_format, MovementType, Player, Players, Symmetry, TileType, World};
 
 fn main() {
-    let config = Config {
-        height: 21,
-        width: 21,
+    env_logger::init();
+    let args: Vec<String> = std::env::args().collect();
+    if let [_, command, before, after, ..] = &args[..] {
+        if command == "diff" {
+            return diff_maps(before, after);
+        }
+    }
+    let template = match arg_value("--template").map(|path| read_map_file(&path)) {
+        Some(Ok(template)) => Some(template),
+        Some(Err(err)) => return println!("Error reading template: {}", err),
+        None => None,
     };
-    let world_builder = WorldBuilder::new(config);
-    let visualiser = Visualiser::new();
-    println!("Running!");
-    let world = world_builder
-        .with_visualiser(visualiser)
+    let config = match &template {
+        Some(template) => Config {
+            height: template.height,
+            width: template.width,
+        },
+        None => Config {
+            height: 21,
+            width: 21,
+        },
+    };
+    let world_builder = WorldBuilder::new(config)
         .set_players(Players::Two)
-        .set_symmetry(Symmetry::Rotational)
+        .set_symmetry(Symmetry::Rotational);
+    let world_builder = if std::env::args().any(|arg| arg == "--debug") {
+        let symmetry = world_builder.symmetry;
+        world_builder.with_observer(Debugger::new(symmetry))
+    } else {
+        world_builder.with_visualiser(Visualiser::new())
+    };
+    let world_builder = match &template {
+        Some(template) => world_builder.apply_template(template),
+        None => world_builder,
+    };
+    println!("Running!");
+    let (result, trace) = match arg_value("--preset") {
+        Some(name) => match Preset::from_name(&name) {
+            Some(preset) => world_builder.apply_preset(preset).build_with_trace(),
+            None => {
+                let names: Vec<&str> = Preset::ALL.iter().map(Preset::name).collect();
+                return println!(
+                    "Unknown preset {name}, expected one of {}",
+                    names.join(", ")
+                );
+            }
+        },
+        None => default_recipe(world_builder),
+    };
+
+    match result {
+        Ok((world, warnings)) => {
+            for warning in warnings {
+                println!("WARNING: {}", warning);
+            }
+            println!("Generated {}x{} map", world.width, world.height);
+            if let Some(report) = &trace.terrain {
+                print!("Terrain:\n{}", report);
+            }
+            if let Some(name) = arg_value("--heatmap") {
+                print_heatmap(&world, &name);
+            }
+            if let Some(path) = arg_value("--save") {
+                if let Err(err) = std::fs::write(&path, map_format::write_map(&world)) {
+                    println!("Error saving map: {}", err);
+                }
+            }
+        }
+        Err(err) => println!("Error generating map: {}", err),
+    }
+    // Saved even when generation fails, to show which step went wrong.
+    if std::env::args().any(|arg| arg == "--trace") {
+        let map_path = arg_value("--save").unwrap_or_else(|| "map.csv".to_string());
+        match trace.save_json(map_path.as_ref()) {
+            Ok(path) => println!("Saved trace to {}", path.display()),
+            Err(err) => println!("Error saving trace: {}", err),
+        }
+    }
+}
+
+fn default_recipe(
+    world_builder: WorldBuilder,
+) -> (
+    Result<(World, Vec<GenerationError>), GenerationError>,
+    GenerationTrace,
+) {
+    world_builder
         .create_headquarters()
         .join_headquarters(TileType::Road)
+        .allocate_properties(PropertyBudget::default())
         .add_forests(0.1)
         .add_mountains(0.05)
         .fill(TileType::Plains)
-        // .create_team_cities()
-        // .create_neutral_cities()
-        // .create_team_factories()
-        // .create_neutral_factories()
         // .create_roads()
         // .add_seas()
-        .build();
+        .build_with_trace()
+}
+
+fn arg_value(flag: &str) -> Option<String> {
+    let args: Vec<String> = std::env::args().collect();
+    let position = args.iter().position(|arg| arg == flag)?;
+    args.get(position + 1).cloned()
+}
+
+fn print_heatmap(world: &World, name: &str) {
+    let values = match name {
+        "distance-1" => fields::distance_to_headquarters(world, Player::One, MovementType::Foot),
+        "distance-2" => fields::distance_to_headquarters(world, Player::Two, MovementType::Foot),
+        "movement" => fields::movement_cost(world, MovementType::Foot),
+        "contested" => fields::contestedness(world, MovementType::Foot),
+        _ => {
+            println!(
+                "Unknown heatmap {name}, expected distance-1, distance-2, movement or contested"
+            );
+            return;
+        }
+    };
+    if let Err(err) = Visualiser::new().print_heatmap(world, &Heatmap::new(name, values)) {
+        println!("Error drawing heatmap: {}", err);
+    }
+}
+
+fn read_map_file(path: &str) -> Result<World, String> {
+    let text = std::fs::read_to_string(path).map_err(|err| format!("{path}: {err}"))?;
+    map_format::read_map(&text).map_err(|err| format!("{path}: {err}"))
+}
 
+fn diff_maps(before: &str, after: &str) {
+    let (before, after) = match (read_map_file(before), read_map_file(after)) {
+        (Ok(before), Ok(after)) => (before, after),
+        (Err(err), _) | (_, Err(err)) => return println!("Error reading map: {}", err),
+    };
+    let diffs = match before.diff(&after) {
+        Ok(diffs) => diffs,
+        Err(err) => return println!("Error diffing maps: {}", err),
+    };
     let visualiser = Visualiser::new();
-    visualiser.visualise(&world)
+    visualiser.print_diff(&before, &after, &diffs);
+    if let Some(path) = arg_value("--png") {
+        if let Err(err) = visualiser.save_diff_png(path.as_ref(), &before, &after, &diffs) {
+            println!("Error saving diff: {}", err);
+        }
+    }
 }